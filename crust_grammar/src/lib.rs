@@ -1,25 +1,125 @@
+pub mod symbol {
+    use std::{
+        collections::HashMap,
+        fmt,
+        sync::{Mutex, OnceLock},
+    };
+
+    /// An interned identifier. Equal names share one slot in a process-wide table, so symbols
+    /// are compared and hashed as plain integers, and `as_str` gives back the text for messages.
+    #[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+    pub struct Symbol(u32);
+
+    #[derive(Default)]
+    struct Interner {
+        symbols: HashMap<&'static str, Symbol>,
+        names: Vec<&'static str>,
+    }
+
+    fn interner() -> &'static Mutex<Interner> {
+        static INTERNER: OnceLock<Mutex<Interner>> = OnceLock::new();
+        INTERNER.get_or_init(Default::default)
+    }
+
+    impl Symbol {
+        pub fn intern(name: &str) -> Self {
+            let mut interner = interner().lock().expect("interner lock poisoned");
+            if let Some(symbol) = interner.symbols.get(name) {
+                return *symbol;
+            }
+            // Names live as long as the table, which is never emptied.
+            let name: &'static str = Box::leak(name.to_string().into_boxed_str());
+            let symbol = Symbol(interner.names.len() as u32);
+            interner.names.push(name);
+            interner.symbols.insert(name, symbol);
+            symbol
+        }
+
+        pub fn as_str(self) -> &'static str {
+            interner().lock().expect("interner lock poisoned").names[self.0 as usize]
+        }
+
+        /// The slot this symbol occupies in the table.
+        pub fn index(self) -> u32 {
+            self.0
+        }
+    }
+
+    impl From<&str> for Symbol {
+        fn from(name: &str) -> Self {
+            Symbol::intern(name)
+        }
+    }
+
+    impl fmt::Debug for Symbol {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{:?}", self.as_str())
+        }
+    }
+
+    impl fmt::Display for Symbol {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(self.as_str())
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    impl serde::Serialize for Symbol {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(self.as_str())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn identical_names_share_a_slot() {
+            let a = Symbol::intern("shared_name");
+            let b = Symbol::intern(&String::from("shared_name"));
+            assert_eq!(a, b);
+            assert_eq!(a.index(), b.index());
+            assert_ne!(a, Symbol::intern("other_name"));
+            assert_eq!(a.as_str(), "shared_name");
+            assert_eq!(a.to_string(), "shared_name");
+        }
+    }
+}
+
 pub mod token {
-    use std::str::FromStr;
+    use std::borrow::Cow;
 
-    use strum::{EnumDiscriminants, EnumString};
+    use strum::EnumDiscriminants;
 
-    #[derive(Debug, PartialEq, EnumDiscriminants)]
-    #[strum_discriminants(derive(EnumString))]
+    use crate::symbol::Symbol;
+
+    /// A token and its payload. Text payloads borrow from the source the token was scanned
+    /// from for `'a` unless they had to be decoded, and [`into_owned`](Token::into_owned)
+    /// copies them out into a `Token<'static>`.
+    #[derive(Debug, Clone, EnumDiscriminants)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
+    #[cfg_attr(feature = "serde", serde(tag = "kind", content = "value"))]
     #[strum_discriminants(name(TokenType))]
-    #[strum_discriminants(strum(ascii_case_insensitive))]
-    pub enum Token {
+    pub enum Token<'a> {
         // Symbols
         LeftParen,
         RightParen,
         LeftBrace,
         RightBrace,
+        LeftBracket,
+        RightBracket,
         Comma,
         Dot,
         Minus,
+        MinusMinus,
         Plus,
+        PlusPlus,
         Semicolon,
         Slash,
         Star,
+        StarStar,
+        Percent,
 
         Bang,
         BangEqual,
@@ -33,7 +133,21 @@ pub mod token {
         BitOr,
         And,
         Or,
+        QuestionQuestion,
+        Question,
+        Arrow,
+        Colon,
+        ColonColon,
+        DotDot,
+        DotDotEqual,
 
+        /// A line break, only scanned when newlines terminate statements.
+        Newline,
+        /// The text of a `// ...` comment after the `//`, only scanned when comments are kept.
+        LineComment(Cow<'a, str>),
+        /// The text of a `/* ... */` comment between its delimiters, only scanned when comments
+        /// are kept.
+        BlockComment(Cow<'a, str>),
         Eof,
 
         // Keywords
@@ -44,6 +158,7 @@ pub mod token {
         False,
         Fn,
         For,
+        In,
         Mut,
         While,
         Loop,
@@ -52,53 +167,377 @@ pub mod token {
         This,
         Super,
         Let,
+        Const,
+        Print,
+        Nil,
 
         // Literals
-        Identifier(String),
-        String(String),
-        Float(f32),
-        Integer(i32),
+        Identifier(Symbol),
+        String(Cow<'a, str>),
+        /// A string literal containing `${expr}`, split into its parts.
+        Interpolation(Vec<StringPart<'a>>),
+        Char(char),
+        Float(f64),
+        Integer(i64),
     }
 
-    #[derive(Debug, PartialEq)]
+    /// A piece of an interpolated string literal: decoded text, or the source of an embedded
+    /// expression, which starts at byte `offset` of the whole source, on `line`.
+    #[derive(Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
+    pub enum StringPart<'a> {
+        Text(Cow<'a, str>),
+        Expression {
+            source: Cow<'a, str>,
+            offset: usize,
+            line: usize,
+        },
+    }
+
+    impl StringPart<'_> {
+        /// The part with its own copy of any text it borrows from the source.
+        pub fn into_owned(self) -> StringPart<'static> {
+            match self {
+                StringPart::Text(text) => StringPart::Text(Cow::Owned(text.into_owned())),
+                StringPart::Expression {
+                    source,
+                    offset,
+                    line,
+                } => StringPart::Expression {
+                    source: Cow::Owned(source.into_owned()),
+                    offset,
+                    line,
+                },
+            }
+        }
+    }
+
+    impl Token<'_> {
+        /// The kind of token without its payload, e.g. `TokenType::Identifier` for every
+        /// identifier, for matching on kinds alone.
+        pub fn kind(&self) -> TokenType {
+            TokenType::from(self)
+        }
+
+        /// The source spelling of operator and punctuation tokens.
+        pub fn symbol(&self) -> Option<&'static str> {
+            let symbol = match self {
+                Token::LeftParen => "(",
+                Token::RightParen => ")",
+                Token::LeftBrace => "{",
+                Token::RightBrace => "}",
+                Token::LeftBracket => "[",
+                Token::RightBracket => "]",
+                Token::Comma => ",",
+                Token::Dot => ".",
+                Token::Minus => "-",
+                Token::MinusMinus => "--",
+                Token::Plus => "+",
+                Token::PlusPlus => "++",
+                Token::Semicolon => ";",
+                Token::Slash => "/",
+                Token::Star => "*",
+                Token::StarStar => "**",
+                Token::Percent => "%",
+                Token::Bang => "!",
+                Token::BangEqual => "!=",
+                Token::Equal => "=",
+                Token::EqualEqual => "==",
+                Token::Greater => ">",
+                Token::GreaterEqual => ">=",
+                Token::Less => "<",
+                Token::LessEqual => "<=",
+                Token::BitAnd => "&",
+                Token::BitOr => "|",
+                Token::And => "and",
+                Token::Or => "or",
+                Token::QuestionQuestion => "??",
+                Token::Question => "?",
+                Token::Arrow => "->",
+                Token::Colon => ":",
+                Token::ColonColon => "::",
+                Token::DotDot => "..",
+                Token::DotDotEqual => "..=",
+                _ => return None,
+            };
+            Some(symbol)
+        }
+
+        /// The token with its own copy of any text its payload borrows from the source, for
+        /// keeping after the source is gone.
+        pub fn into_owned(self) -> Token<'static> {
+            match self {
+                Token::LineComment(text) => Token::LineComment(Cow::Owned(text.into_owned())),
+                Token::BlockComment(text) => Token::BlockComment(Cow::Owned(text.into_owned())),
+                Token::String(text) => Token::String(Cow::Owned(text.into_owned())),
+                Token::Interpolation(parts) => {
+                    Token::Interpolation(parts.into_iter().map(StringPart::into_owned).collect())
+                }
+                Token::LeftParen => Token::LeftParen,
+                Token::RightParen => Token::RightParen,
+                Token::LeftBrace => Token::LeftBrace,
+                Token::RightBrace => Token::RightBrace,
+                Token::LeftBracket => Token::LeftBracket,
+                Token::RightBracket => Token::RightBracket,
+                Token::Comma => Token::Comma,
+                Token::Dot => Token::Dot,
+                Token::Minus => Token::Minus,
+                Token::MinusMinus => Token::MinusMinus,
+                Token::Plus => Token::Plus,
+                Token::PlusPlus => Token::PlusPlus,
+                Token::Semicolon => Token::Semicolon,
+                Token::Slash => Token::Slash,
+                Token::Star => Token::Star,
+                Token::StarStar => Token::StarStar,
+                Token::Percent => Token::Percent,
+                Token::Bang => Token::Bang,
+                Token::BangEqual => Token::BangEqual,
+                Token::Equal => Token::Equal,
+                Token::EqualEqual => Token::EqualEqual,
+                Token::Greater => Token::Greater,
+                Token::GreaterEqual => Token::GreaterEqual,
+                Token::Less => Token::Less,
+                Token::LessEqual => Token::LessEqual,
+                Token::BitAnd => Token::BitAnd,
+                Token::BitOr => Token::BitOr,
+                Token::And => Token::And,
+                Token::Or => Token::Or,
+                Token::QuestionQuestion => Token::QuestionQuestion,
+                Token::Question => Token::Question,
+                Token::Arrow => Token::Arrow,
+                Token::Colon => Token::Colon,
+                Token::ColonColon => Token::ColonColon,
+                Token::DotDot => Token::DotDot,
+                Token::DotDotEqual => Token::DotDotEqual,
+                Token::Newline => Token::Newline,
+                Token::Eof => Token::Eof,
+                Token::Class => Token::Class,
+                Token::If => Token::If,
+                Token::Else => Token::Else,
+                Token::True => Token::True,
+                Token::False => Token::False,
+                Token::Fn => Token::Fn,
+                Token::For => Token::For,
+                Token::In => Token::In,
+                Token::Mut => Token::Mut,
+                Token::While => Token::While,
+                Token::Loop => Token::Loop,
+                Token::Break => Token::Break,
+                Token::Return => Token::Return,
+                Token::This => Token::This,
+                Token::Super => Token::Super,
+                Token::Let => Token::Let,
+                Token::Const => Token::Const,
+                Token::Print => Token::Print,
+                Token::Nil => Token::Nil,
+                Token::Identifier(symbol) => Token::Identifier(symbol),
+                Token::Char(char) => Token::Char(char),
+                Token::Float(number) => Token::Float(number),
+                Token::Integer(number) => Token::Integer(number),
+            }
+        }
+    }
+
+    /// Structural equality: tokens are equal when they are the same kind with the same payload,
+    /// and two `Float(NaN)` tokens count as equal so a token always equals itself.
+    impl PartialEq for Token<'_> {
+        fn eq(&self, other: &Self) -> bool {
+            match (self, other) {
+                (Token::Identifier(a), Token::Identifier(b)) => a == b,
+                (Token::String(a), Token::String(b)) => a == b,
+                (Token::Interpolation(a), Token::Interpolation(b)) => a == b,
+                (Token::LineComment(a), Token::LineComment(b))
+                | (Token::BlockComment(a), Token::BlockComment(b)) => a == b,
+                (Token::Char(a), Token::Char(b)) => a == b,
+                (Token::Float(a), Token::Float(b)) => a == b || (a.is_nan() && b.is_nan()),
+                (Token::Integer(a), Token::Integer(b)) => a == b,
+                _ => self.kind() == other.kind(),
+            }
+        }
+    }
+
+    /// A scanned token together with where it came from: `lexeme` is the exact source text,
+    /// starting at byte `offset` and spanning `length` bytes.
+    #[derive(Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
     pub struct SourceToken {
-        pub token: Token,
+        #[cfg_attr(feature = "serde", serde(flatten))]
+        pub token: Token<'static>,
+        pub lexeme: String,
         pub offset: usize,
         pub line: usize,
         pub length: usize,
+        /// The 1-based display column the token starts at, or 0 for tokens that were not
+        /// scanned from source. It is not part of the JSON format.
+        #[cfg_attr(feature = "serde", serde(skip))]
+        pub column: usize,
     }
     impl SourceToken {
-        pub fn new(token: Token, offset: usize, line: usize, length: usize) -> Self {
+        pub fn new(token: Token<'static>, lexeme: &str, offset: usize, line: usize) -> Self {
             Self {
                 token,
+                lexeme: lexeme.to_string(),
                 offset,
                 line,
-                length,
+                length: lexeme.len(),
+                column: 0,
+            }
+        }
+    }
+
+    /// A [`SourceToken`] whose lexeme and payload borrow from the source instead of owning a
+    /// copy, so scanning allocates nothing for most tokens. Identifiers are interned either
+    /// way, and only string literals with escapes, whose text is decoded, own their text.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct BorrowedToken<'a> {
+        pub token: Token<'a>,
+        pub lexeme: &'a str,
+        pub offset: usize,
+        pub line: usize,
+        /// The 1-based display column the token starts at.
+        pub column: usize,
+    }
+
+    impl BorrowedToken<'_> {
+        /// The token with its own copy of the lexeme, for keeping after the source is gone.
+        pub fn into_owned(self) -> SourceToken {
+            SourceToken {
+                token: self.token.into_owned(),
+                lexeme: self.lexeme.to_string(),
+                offset: self.offset,
+                line: self.line,
+                length: self.lexeme.len(),
+                column: self.column,
             }
         }
     }
 
-    pub fn try_as_keyword(text: &str) -> Option<Token> {
-        match TokenType::from_str(text) {
-            Ok(token_type) => match token_type {
-                TokenType::Class => Some(Token::Class),
-                TokenType::If => Some(Token::If),
-                TokenType::Else => Some(Token::Else),
-                TokenType::True => Some(Token::True),
-                TokenType::False => Some(Token::False),
-                TokenType::Fn => Some(Token::Fn),
-                TokenType::For => Some(Token::For),
-                TokenType::Mut => Some(Token::Mut),
-                TokenType::While => Some(Token::While),
-                TokenType::Loop => Some(Token::Loop),
-                TokenType::Break => Some(Token::Break),
-                TokenType::Return => Some(Token::Return),
-                TokenType::This => Some(Token::This),
-                TokenType::Super => Some(Token::Super),
-                TokenType::Let => Some(Token::Let),
-                _ => None,
-            },
-            Err(_) => None,
+    /// The keyword spelled exactly `text`. Keywords are case-sensitive, so `If` or `TRUE` are
+    /// ordinary identifiers.
+    pub fn try_as_keyword(text: &str) -> Option<Token<'static>> {
+        let keyword = match text {
+            "class" => Token::Class,
+            "if" => Token::If,
+            "else" => Token::Else,
+            "true" => Token::True,
+            "false" => Token::False,
+            "fn" => Token::Fn,
+            "for" => Token::For,
+            "in" => Token::In,
+            "mut" => Token::Mut,
+            "while" => Token::While,
+            "loop" => Token::Loop,
+            "break" => Token::Break,
+            "return" => Token::Return,
+            "this" => Token::This,
+            "super" => Token::Super,
+            "let" => Token::Let,
+            "const" => Token::Const,
+            "print" => Token::Print,
+            "nil" => Token::Nil,
+            "and" => Token::And,
+            "or" => Token::Or,
+            _ => return None,
+        };
+        Some(keyword)
+    }
+
+    /// Renders tokens as a pretty-printed JSON array with one object per token, e.g.
+    /// `{ "kind": "Integer", "value": 1, "lexeme": "1", "offset": 8, "line": 1, "length": 1 }`.
+    /// Tokens without a payload have no `value` field.
+    #[cfg(feature = "serde")]
+    pub fn tokens_to_json(tokens: &[SourceToken]) -> String {
+        serde_json::to_string_pretty(tokens).expect("tokens are always serializable")
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn every_keyword_maps_to_its_token() {
+            let keywords = [
+                ("class", Token::Class),
+                ("if", Token::If),
+                ("else", Token::Else),
+                ("true", Token::True),
+                ("false", Token::False),
+                ("fn", Token::Fn),
+                ("for", Token::For),
+                ("in", Token::In),
+                ("mut", Token::Mut),
+                ("while", Token::While),
+                ("loop", Token::Loop),
+                ("break", Token::Break),
+                ("return", Token::Return),
+                ("this", Token::This),
+                ("super", Token::Super),
+                ("let", Token::Let),
+                ("const", Token::Const),
+                ("print", Token::Print),
+                ("nil", Token::Nil),
+                ("and", Token::And),
+                ("or", Token::Or),
+            ];
+            for (text, token) in keywords {
+                assert_eq!(try_as_keyword(text), Some(token), "{text}");
+            }
+            for text in ["", "iff", "lets", "x", "_", "Eof", "identifier"] {
+                assert_eq!(try_as_keyword(text), None, "{text}");
+            }
+        }
+
+        #[test]
+        fn keywords_are_case_sensitive() {
+            assert_eq!(try_as_keyword("true"), Some(Token::True));
+            assert_eq!(try_as_keyword("True"), None);
+            assert_eq!(try_as_keyword("TRUE"), None);
+            assert_eq!(try_as_keyword("If"), None);
+            assert_eq!(try_as_keyword("fn"), Some(Token::Fn));
+        }
+
+        #[test]
+        fn kind_ignores_the_payload() {
+            assert_eq!(Token::Identifier("a".into()).kind(), TokenType::Identifier);
+            assert_eq!(Token::Integer(1).kind(), Token::Integer(2).kind());
+            assert_ne!(Token::Integer(1).kind(), Token::Float(1.0).kind());
+            assert_eq!(Token::Semicolon.kind(), TokenType::Semicolon);
+        }
+
+        #[test]
+        fn nan_tokens_are_structurally_equal() {
+            assert_eq!(Token::Float(f64::NAN), Token::Float(f64::NAN));
+            assert_eq!(Token::Float(1.5), Token::Float(1.5));
+            assert_ne!(Token::Float(f64::NAN), Token::Float(1.5));
+            assert_ne!(Token::Float(1.0), Token::Integer(1));
+            assert_ne!(Token::Identifier("a".into()), Token::String("a".into()));
+            assert_eq!(Token::Semicolon, Token::Semicolon);
+            assert_ne!(Token::Semicolon, Token::Comma);
+        }
+
+        #[cfg(feature = "serde")]
+        #[test]
+        fn tokens_to_json_shape() {
+            let tokens = vec![
+                SourceToken::new(Token::Let, "let", 0, 1),
+                SourceToken::new(Token::Identifier("x".into()), "x", 4, 1),
+                SourceToken::new(Token::Equal, "=", 6, 1),
+                SourceToken::new(Token::Integer(1), "1", 8, 1),
+                SourceToken::new(Token::Semicolon, ";", 9, 1),
+                SourceToken::new(Token::Eof, "", 10, 1),
+            ];
+            let json: serde_json::Value = serde_json::from_str(&tokens_to_json(&tokens)).unwrap();
+            assert_eq!(
+                json,
+                serde_json::json!([
+                    { "kind": "Let", "lexeme": "let", "offset": 0, "line": 1, "length": 3 },
+                    { "kind": "Identifier", "value": "x", "lexeme": "x", "offset": 4, "line": 1, "length": 1 },
+                    { "kind": "Equal", "lexeme": "=", "offset": 6, "line": 1, "length": 1 },
+                    { "kind": "Integer", "value": 1, "lexeme": "1", "offset": 8, "line": 1, "length": 1 },
+                    { "kind": "Semicolon", "lexeme": ";", "offset": 9, "line": 1, "length": 1 },
+                    { "kind": "Eof", "lexeme": "", "offset": 10, "line": 1, "length": 0 },
+                ])
+            );
         }
     }
 }
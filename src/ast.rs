@@ -1,61 +1,502 @@
-use crust_grammar::token::Token;
+use crust_grammar::{
+    symbol::Symbol,
+    token::{SourceToken, Token},
+};
 
 use crate::util::CrustCoreResult;
 
+/// An expression node. Operators and literals keep the `SourceToken` they were parsed from,
+/// and nodes without one of their own record the `line` they start on, so that runtime errors
+/// can point back into the source.
+///
+/// Assignment binds loosest, and the conditional `a ? b : c` sits just above it, looser than
+/// every logical and binary operator. A conditional nests to the right, so
+/// `a ? b : c ? d : e` is `a ? b : (c ? d : e)`, `x = a or b ? c : d` assigns the whole
+/// conditional, and `a ? b : c = d` has no valid assignment target.
+#[derive(Debug, Clone, PartialEq)]
 pub enum Expression {
+    Assign {
+        name: Symbol,
+        line: usize,
+        value: Box<Expression>,
+    },
     Binary {
         left: Box<Expression>,
-        op: Token,
+        op: SourceToken,
         right: Box<Expression>,
     },
+    /// `{ statements value }`, evaluating to `value` or to `nil` when the block ends in `;`.
+    Block {
+        statements: Vec<Statement>,
+        value: Option<Box<Expression>>,
+        line: usize,
+    },
+    Call {
+        callee: Box<Expression>,
+        line: usize,
+        args: Vec<Expression>,
+    },
+    Conditional {
+        condition: Box<Expression>,
+        then_branch: Box<Expression>,
+        else_branch: Box<Expression>,
+    },
     Grouping {
         expr: Box<Expression>,
     },
+    /// `"a ${b} c"`, the text and embedded expressions of a string in order, joined into one
+    /// string when evaluated.
+    Interpolation {
+        parts: Vec<Expression>,
+        line: usize,
+    },
+    /// `target[index]`, with the line of the `[`.
+    Index {
+        target: Box<Expression>,
+        index: Box<Expression>,
+        line: usize,
+    },
+    /// `fn(params) { body }`, an anonymous function. A final expression in the body is
+    /// parsed as its `return`.
+    Lambda {
+        params: Vec<Parameter>,
+        body: Vec<Statement>,
+        line: usize,
+    },
     Literal {
-        value: Token,
+        value: SourceToken,
+    },
+    Logical {
+        left: Box<Expression>,
+        op: SourceToken,
+        right: Box<Expression>,
+    },
+    /// `a::b::c`, a name qualified by the modules it is in. `segments` is never empty.
+    Path {
+        segments: Vec<Symbol>,
+        line: usize,
     },
     Unary {
-        op: Token,
+        op: SourceToken,
         right: Box<Expression>,
     },
+    Variable {
+        name: Symbol,
+        line: usize,
+    },
 }
 
 impl Expression {
+    pub fn binary(left: Expression, op: SourceToken, right: Expression) -> Self {
+        Expression::Binary {
+            left: Box::new(left),
+            op,
+            right: Box::new(right),
+        }
+    }
+
+    pub fn logical(left: Expression, op: SourceToken, right: Expression) -> Self {
+        Expression::Logical {
+            left: Box::new(left),
+            op,
+            right: Box::new(right),
+        }
+    }
+
+    pub fn unary(op: SourceToken, right: Expression) -> Self {
+        Expression::Unary {
+            op,
+            right: Box::new(right),
+        }
+    }
+
+    pub fn grouping(expr: Expression) -> Self {
+        Expression::Grouping {
+            expr: Box::new(expr),
+        }
+    }
+
+    pub fn literal(value: SourceToken) -> Self {
+        Expression::Literal { value }
+    }
+
     pub fn accept<T>(&self, visitor: &dyn Visitor<T>) -> CrustCoreResult<T> {
         visitor.visit(self)
     }
+
+    /// Splits the left operand of a chain like `a + b - c`, whose tree nests every earlier
+    /// operator in the left operand of the next, into the chain's first operand and the Binary
+    /// and Logical nodes above it with their operators and right operands, innermost first.
+    /// Passes over the tree loop over these rather than recursing into each left operand, as
+    /// a long chain would overflow the stack.
+    pub fn chain(&self) -> (&Expression, Vec<(&Expression, &SourceToken, &Expression)>) {
+        let mut chain = vec![];
+        let mut first = self;
+        while let Expression::Binary { left, op, right } | Expression::Logical { left, op, right } =
+            first
+        {
+            chain.push((first, op, &**right));
+            first = left;
+        }
+        chain.reverse();
+        (first, chain)
+    }
+
+    /// The line of the token that identifies this expression: its operator, literal or name.
+    pub fn line(&self) -> usize {
+        match self {
+            Expression::Assign { line, .. }
+            | Expression::Block { line, .. }
+            | Expression::Call { line, .. }
+            | Expression::Index { line, .. }
+            | Expression::Interpolation { line, .. }
+            | Expression::Lambda { line, .. }
+            | Expression::Path { line, .. }
+            | Expression::Variable { line, .. } => *line,
+            Expression::Binary { op, .. }
+            | Expression::Logical { op, .. }
+            | Expression::Unary { op, .. } => op.line,
+            Expression::Conditional { condition, .. } => condition.line(),
+            Expression::Grouping { expr } => expr.line(),
+            Expression::Literal { value } => value.line,
+        }
+    }
 }
 
 pub trait Visitor<T> {
     fn visit(&self, expression: &Expression) -> CrustCoreResult<T> {
         match expression {
+            Expression::Assign { name, line, value } => self.visit_assign(*name, *line, value),
             Expression::Binary { left, op, right } => self.visit_binary(left, op, right),
+            Expression::Block {
+                statements,
+                value,
+                line,
+            } => self.visit_block_expression(statements, value.as_deref(), *line),
+            Expression::Call { callee, line, args } => self.visit_call(callee, *line, args),
+            Expression::Conditional {
+                condition,
+                then_branch,
+                else_branch,
+            } => self.visit_conditional(condition, then_branch, else_branch),
             Expression::Grouping { expr } => self.visit_grouping(expr),
+            Expression::Index {
+                target,
+                index,
+                line,
+            } => self.visit_index(target, index, *line),
+            Expression::Interpolation { parts, line } => self.visit_interpolation(parts, *line),
+            Expression::Lambda { params, body, line } => self.visit_lambda(params, body, *line),
             Expression::Literal { value } => self.visit_literal(value),
+            Expression::Logical { left, op, right } => self.visit_logical(left, op, right),
+            Expression::Path { segments, line } => self.visit_path(segments, *line),
             Expression::Unary { op, right } => self.visit_unary(op, right),
+            Expression::Variable { name, line } => self.visit_variable(*name, *line),
         }
     }
 
-    fn visit_binary(&self, left: &Expression, op: &Token, right: &Expression)
-        -> CrustCoreResult<T>;
+    fn visit_assign(&self, name: Symbol, line: usize, value: &Expression) -> CrustCoreResult<T>;
+    fn visit_binary(
+        &self,
+        left: &Expression,
+        op: &SourceToken,
+        right: &Expression,
+    ) -> CrustCoreResult<T>;
+    fn visit_block_expression(
+        &self,
+        statements: &[Statement],
+        value: Option<&Expression>,
+        line: usize,
+    ) -> CrustCoreResult<T>;
+    fn visit_call(
+        &self,
+        callee: &Expression,
+        line: usize,
+        args: &[Expression],
+    ) -> CrustCoreResult<T>;
+    fn visit_conditional(
+        &self,
+        condition: &Expression,
+        then_branch: &Expression,
+        else_branch: &Expression,
+    ) -> CrustCoreResult<T>;
     fn visit_grouping(&self, expr: &Expression) -> CrustCoreResult<T>;
-    fn visit_literal(&self, value: &Token) -> CrustCoreResult<T>;
-    fn visit_unary(&self, op: &Token, right: &Expression) -> CrustCoreResult<T>;
+    fn visit_index(
+        &self,
+        target: &Expression,
+        index: &Expression,
+        line: usize,
+    ) -> CrustCoreResult<T>;
+    fn visit_interpolation(&self, parts: &[Expression], line: usize) -> CrustCoreResult<T>;
+    fn visit_lambda(
+        &self,
+        params: &[Parameter],
+        body: &[Statement],
+        line: usize,
+    ) -> CrustCoreResult<T>;
+    fn visit_literal(&self, value: &SourceToken) -> CrustCoreResult<T>;
+    fn visit_logical(
+        &self,
+        left: &Expression,
+        op: &SourceToken,
+        right: &Expression,
+    ) -> CrustCoreResult<T>;
+    fn visit_path(&self, segments: &[Symbol], line: usize) -> CrustCoreResult<T>;
+    fn visit_unary(&self, op: &SourceToken, right: &Expression) -> CrustCoreResult<T>;
+    fn visit_variable(&self, name: Symbol, line: usize) -> CrustCoreResult<T>;
+}
+
+/// A function parameter with its optional type annotation, e.g. `a: Int`. Annotations are
+/// recorded for tooling but not checked by the interpreter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub name: Symbol,
+    pub annotation: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Block {
+        statements: Vec<Statement>,
+        line: usize,
+    },
+    Expression {
+        expr: Expression,
+    },
+    /// `for name in start..end { body }`, counting through the Integers of the range.
+    For {
+        name: Symbol,
+        start: Expression,
+        end: Expression,
+        inclusive: bool,
+        body: Vec<Statement>,
+        line: usize,
+    },
+    Function {
+        name: Symbol,
+        params: Vec<Parameter>,
+        return_type: Option<String>,
+        body: Vec<Statement>,
+        line: usize,
+    },
+    Const {
+        name: Symbol,
+        initializer: Expression,
+        line: usize,
+    },
+    /// `let mut name: annotation = initializer;`, where all but the name are optional.
+    Let {
+        name: Symbol,
+        mutable: bool,
+        annotation: Option<String>,
+        initializer: Option<Expression>,
+        line: usize,
+    },
+    Print {
+        expr: Expression,
+    },
+    Return {
+        value: Option<Expression>,
+        line: usize,
+    },
+}
+
+impl Statement {
+    pub fn accept<T>(&self, visitor: &dyn StatementVisitor<T>) -> CrustCoreResult<T> {
+        visitor.visit(self)
+    }
+
+    /// The line the statement starts on: its keyword, its `{`, or its expression.
+    pub fn line(&self) -> usize {
+        match self {
+            Statement::Block { line, .. }
+            | Statement::For { line, .. }
+            | Statement::Function { line, .. }
+            | Statement::Const { line, .. }
+            | Statement::Let { line, .. }
+            | Statement::Return { line, .. } => *line,
+            Statement::Expression { expr } | Statement::Print { expr } => expr.line(),
+        }
+    }
 }
 
+pub trait StatementVisitor<T> {
+    fn visit(&self, statement: &Statement) -> CrustCoreResult<T> {
+        match statement {
+            Statement::Block { statements, .. } => self.visit_block(statements),
+            Statement::Expression { expr } => self.visit_expression(expr),
+            Statement::For {
+                name,
+                start,
+                end,
+                inclusive,
+                body,
+                ..
+            } => self.visit_for(*name, start, end, *inclusive, body),
+            Statement::Function {
+                name,
+                params,
+                return_type,
+                body,
+                ..
+            } => self.visit_function(*name, params, return_type.as_deref(), body),
+            Statement::Const {
+                name, initializer, ..
+            } => self.visit_const(*name, initializer),
+            Statement::Let {
+                name,
+                mutable,
+                annotation,
+                initializer,
+                ..
+            } => self.visit_let(*name, *mutable, annotation.as_deref(), initializer.as_ref()),
+            Statement::Print { expr } => self.visit_print(expr),
+            Statement::Return { value, .. } => self.visit_return(value.as_ref()),
+        }
+    }
+
+    fn visit_block(&self, statements: &[Statement]) -> CrustCoreResult<T>;
+    fn visit_expression(&self, expr: &Expression) -> CrustCoreResult<T>;
+    fn visit_for(
+        &self,
+        name: Symbol,
+        start: &Expression,
+        end: &Expression,
+        inclusive: bool,
+        body: &[Statement],
+    ) -> CrustCoreResult<T>;
+    fn visit_function(
+        &self,
+        name: Symbol,
+        params: &[Parameter],
+        return_type: Option<&str>,
+        body: &[Statement],
+    ) -> CrustCoreResult<T>;
+    fn visit_const(&self, name: Symbol, initializer: &Expression) -> CrustCoreResult<T>;
+    fn visit_let(
+        &self,
+        name: Symbol,
+        mutable: bool,
+        annotation: Option<&str>,
+        initializer: Option<&Expression>,
+    ) -> CrustCoreResult<T>;
+    fn visit_print(&self, expr: &Expression) -> CrustCoreResult<T>;
+    fn visit_return(&self, value: Option<&Expression>) -> CrustCoreResult<T>;
+}
+
+/// Prints the tree in prefix notation, with every operator in parentheses, e.g.
+/// `( * ( - 2.0 ) ( group 15 ) )`. [`SourcePrinter`] prints it back as source instead.
 pub struct AstPrinter;
+
+/// `a::b::c`, the segments of a path in source form.
+pub(crate) fn print_path(segments: &[Symbol]) -> String {
+    segments
+        .iter()
+        .map(|segment| segment.to_string())
+        .collect::<Vec<String>>()
+        .join("::")
+}
+
+/// `name` or `name: annotation`.
+fn parameter(param: &Parameter) -> String {
+    match &param.annotation {
+        Some(annotation) => format!("{}: {}", param.name, annotation),
+        None => param.name.to_string(),
+    }
+}
+
+fn operator(op: &SourceToken) -> String {
+    match op.token.symbol() {
+        Some(symbol) => symbol.to_string(),
+        None => format!("{:?}", op.token),
+    }
+}
+impl AstPrinter {
+    fn print_block(&self, statements: &[Statement]) -> CrustCoreResult<String> {
+        let statements = statements
+            .iter()
+            .map(|statement| statement.accept(self))
+            .collect::<CrustCoreResult<Vec<String>>>()?;
+        if statements.is_empty() {
+            Ok("{ }".to_string())
+        } else {
+            Ok(format!("{{ {} }}", statements.join(" ")))
+        }
+    }
+}
+
 impl Visitor<String> for AstPrinter {
+    fn visit_assign(
+        &self,
+        name: Symbol,
+        _line: usize,
+        value: &Expression,
+    ) -> CrustCoreResult<String> {
+        let res = format!("( = {} {} )", name, value.accept(self)?);
+        Ok(res)
+    }
+
     fn visit_binary(
         &self,
         left: &Expression,
-        op: &Token,
+        op: &SourceToken,
         right: &Expression,
+    ) -> CrustCoreResult<String> {
+        let (first, chain) = left.chain();
+        let mut res = first.accept(self)?;
+        for (_, op, right) in chain {
+            res = format!("( {} {} {} )", operator(op), res, right.accept(self)?);
+        }
+        let res = format!("( {} {} {} )", operator(op), res, right.accept(self)?);
+        Ok(res)
+    }
+
+    fn visit_block_expression(
+        &self,
+        statements: &[Statement],
+        value: Option<&Expression>,
+        _line: usize,
+    ) -> CrustCoreResult<String> {
+        let mut parts = statements
+            .iter()
+            .map(|statement| statement.accept(self))
+            .collect::<CrustCoreResult<Vec<String>>>()?;
+        if let Some(value) = value {
+            parts.push(value.accept(self)?);
+        }
+        if parts.is_empty() {
+            Ok("{ }".to_string())
+        } else {
+            Ok(format!("{{ {} }}", parts.join(" ")))
+        }
+    }
+
+    fn visit_call(
+        &self,
+        callee: &Expression,
+        _line: usize,
+        args: &[Expression],
+    ) -> CrustCoreResult<String> {
+        let mut res = format!("( call {}", callee.accept(self)?);
+        for arg in args {
+            res.push(' ');
+            res.push_str(&arg.accept(self)?);
+        }
+        res.push_str(" )");
+        Ok(res)
+    }
+
+    fn visit_conditional(
+        &self,
+        condition: &Expression,
+        then_branch: &Expression,
+        else_branch: &Expression,
     ) -> CrustCoreResult<String> {
         let res = format!(
-            "( {:?} {} {} )",
-            op,
-            left.accept(self)?,
-            right.accept(self)?
+            "( ? {} {} {} )",
+            condition.accept(self)?,
+            then_branch.accept(self)?,
+            else_branch.accept(self)?
         );
         Ok(res)
     }
@@ -65,19 +506,665 @@ impl Visitor<String> for AstPrinter {
         Ok(res)
     }
 
-    fn visit_literal(&self, value: &Token) -> CrustCoreResult<String> {
-        let res = match value {
+    fn visit_index(
+        &self,
+        target: &Expression,
+        index: &Expression,
+        _line: usize,
+    ) -> CrustCoreResult<String> {
+        let res = format!("( index {} {} )", target.accept(self)?, index.accept(self)?);
+        Ok(res)
+    }
+
+    fn visit_interpolation(&self, parts: &[Expression], _line: usize) -> CrustCoreResult<String> {
+        let mut res = "( interpolate".to_string();
+        for part in parts {
+            res.push(' ');
+            res.push_str(&part.accept(self)?);
+        }
+        res.push_str(" )");
+        Ok(res)
+    }
+
+    fn visit_lambda(
+        &self,
+        params: &[Parameter],
+        body: &[Statement],
+        _line: usize,
+    ) -> CrustCoreResult<String> {
+        let params = params.iter().map(parameter).collect::<Vec<String>>();
+        let res = format!(
+            "( fn ( {} ) {} )",
+            params.join(" "),
+            self.print_block(body)?
+        );
+        Ok(res)
+    }
+
+    fn visit_literal(&self, value: &SourceToken) -> CrustCoreResult<String> {
+        // Floats always show a decimal point and Integers never do, so `2.0` and `2` differ.
+        let res = match &value.token {
             Token::Identifier(id) => format!("{:?}", id),
             Token::String(id) => format!("{:?}", id),
-            Token::Float(id) => format!("{:?}", id),
-            Token::Integer(id) => format!("{:?}", id),
+            Token::Char(id) => format!("{:?}", id),
+            Token::Float(id) if id.is_finite() && id.fract() == 0.0 => format!("{id:.1}"),
+            Token::Float(id) => id.to_string(),
+            Token::Integer(id) => id.to_string(),
+            Token::True => "true".to_string(),
+            Token::False => "false".to_string(),
             _ => "nil".to_string(),
         };
         Ok(res)
     }
 
-    fn visit_unary(&self, op: &Token, right: &Expression) -> CrustCoreResult<String> {
-        let res = format!("( {:?} {} )", op, right.accept(self)?);
+    fn visit_logical(
+        &self,
+        left: &Expression,
+        op: &SourceToken,
+        right: &Expression,
+    ) -> CrustCoreResult<String> {
+        self.visit_binary(left, op, right)
+    }
+
+    fn visit_path(&self, segments: &[Symbol], _line: usize) -> CrustCoreResult<String> {
+        Ok(print_path(segments))
+    }
+
+    fn visit_unary(&self, op: &SourceToken, right: &Expression) -> CrustCoreResult<String> {
+        let res = format!("( {} {} )", operator(op), right.accept(self)?);
+        Ok(res)
+    }
+
+    fn visit_variable(&self, name: Symbol, _line: usize) -> CrustCoreResult<String> {
+        Ok(name.to_string())
+    }
+}
+
+impl StatementVisitor<String> for AstPrinter {
+    fn visit_block(&self, statements: &[Statement]) -> CrustCoreResult<String> {
+        self.print_block(statements)
+    }
+
+    fn visit_expression(&self, expr: &Expression) -> CrustCoreResult<String> {
+        let res = format!("{} ;", expr.accept(self)?);
+        Ok(res)
+    }
+
+    fn visit_for(
+        &self,
+        name: Symbol,
+        start: &Expression,
+        end: &Expression,
+        inclusive: bool,
+        body: &[Statement],
+    ) -> CrustCoreResult<String> {
+        let res = format!(
+            "( for {} ( {} {} {} ) {} )",
+            name,
+            if inclusive { "..=" } else { ".." },
+            start.accept(self)?,
+            end.accept(self)?,
+            self.print_block(body)?
+        );
+        Ok(res)
+    }
+
+    fn visit_function(
+        &self,
+        name: Symbol,
+        params: &[Parameter],
+        return_type: Option<&str>,
+        body: &[Statement],
+    ) -> CrustCoreResult<String> {
+        let params = params.iter().map(parameter).collect::<Vec<String>>();
+        let return_type = match return_type {
+            Some(return_type) => format!("-> {} ", return_type),
+            None => String::new(),
+        };
+        let res = format!(
+            "( fn {} ( {} ) {}{} )",
+            name,
+            params.join(" "),
+            return_type,
+            self.print_block(body)?
+        );
+        Ok(res)
+    }
+
+    fn visit_const(&self, name: Symbol, initializer: &Expression) -> CrustCoreResult<String> {
+        let res = format!("( const {} {} ) ;", name, initializer.accept(self)?);
+        Ok(res)
+    }
+
+    fn visit_let(
+        &self,
+        name: Symbol,
+        mutable: bool,
+        annotation: Option<&str>,
+        initializer: Option<&Expression>,
+    ) -> CrustCoreResult<String> {
+        let mut res = if mutable {
+            format!("( let mut {}", name)
+        } else {
+            format!("( let {}", name)
+        };
+        if let Some(annotation) = annotation {
+            res.push_str(&format!(": {annotation}"));
+        }
+        if let Some(initializer) = initializer {
+            res.push(' ');
+            res.push_str(&initializer.accept(self)?);
+        }
+        res.push_str(" ) ;");
+        Ok(res)
+    }
+
+    fn visit_print(&self, expr: &Expression) -> CrustCoreResult<String> {
+        let res = format!("( print {} ) ;", expr.accept(self)?);
+        Ok(res)
+    }
+
+    fn visit_return(&self, value: Option<&Expression>) -> CrustCoreResult<String> {
+        let res = match value {
+            Some(value) => format!("( return {} ) ;", value.accept(self)?),
+            None => "( return ) ;".to_string(),
+        };
+        Ok(res)
+    }
+}
+
+/// Renders expressions in reverse Polish notation, e.g. `(1 + 2) * 3` as `1 2 + 3 *`.
+/// Operands always come before the operator applied to them, groupings add nothing and calls
+/// list their arguments and callee before `call`.
+pub struct RpnPrinter;
+impl Visitor<String> for RpnPrinter {
+    fn visit_assign(
+        &self,
+        name: Symbol,
+        _line: usize,
+        value: &Expression,
+    ) -> CrustCoreResult<String> {
+        let res = format!("{} {} =", value.accept(self)?, name);
+        Ok(res)
+    }
+
+    fn visit_binary(
+        &self,
+        left: &Expression,
+        op: &SourceToken,
+        right: &Expression,
+    ) -> CrustCoreResult<String> {
+        let res = format!(
+            "{} {} {}",
+            left.accept(self)?,
+            right.accept(self)?,
+            operator(op)
+        );
+        Ok(res)
+    }
+
+    /// Blocks contain statements, which have no postfix form, so they print as in
+    /// [`AstPrinter`].
+    fn visit_block_expression(
+        &self,
+        statements: &[Statement],
+        value: Option<&Expression>,
+        line: usize,
+    ) -> CrustCoreResult<String> {
+        AstPrinter {}.visit_block_expression(statements, value, line)
+    }
+
+    fn visit_call(
+        &self,
+        callee: &Expression,
+        _line: usize,
+        args: &[Expression],
+    ) -> CrustCoreResult<String> {
+        let mut res = String::new();
+        for arg in args {
+            res.push_str(&arg.accept(self)?);
+            res.push(' ');
+        }
+        res.push_str(&format!("{} call", callee.accept(self)?));
+        Ok(res)
+    }
+
+    fn visit_conditional(
+        &self,
+        condition: &Expression,
+        then_branch: &Expression,
+        else_branch: &Expression,
+    ) -> CrustCoreResult<String> {
+        let res = format!(
+            "{} {} {} ?:",
+            condition.accept(self)?,
+            then_branch.accept(self)?,
+            else_branch.accept(self)?
+        );
+        Ok(res)
+    }
+
+    fn visit_grouping(&self, expr: &Expression) -> CrustCoreResult<String> {
+        expr.accept(self)
+    }
+
+    fn visit_index(
+        &self,
+        target: &Expression,
+        index: &Expression,
+        _line: usize,
+    ) -> CrustCoreResult<String> {
+        let res = format!("{} {} index", target.accept(self)?, index.accept(self)?);
+        Ok(res)
+    }
+
+    fn visit_interpolation(&self, parts: &[Expression], _line: usize) -> CrustCoreResult<String> {
+        let mut res = String::new();
+        for part in parts {
+            res.push_str(&part.accept(self)?);
+            res.push(' ');
+        }
+        res.push_str("interpolate");
+        Ok(res)
+    }
+
+    fn visit_lambda(
+        &self,
+        params: &[Parameter],
+        body: &[Statement],
+        line: usize,
+    ) -> CrustCoreResult<String> {
+        AstPrinter {}.visit_lambda(params, body, line)
+    }
+
+    fn visit_literal(&self, value: &SourceToken) -> CrustCoreResult<String> {
+        AstPrinter {}.visit_literal(value)
+    }
+
+    fn visit_logical(
+        &self,
+        left: &Expression,
+        op: &SourceToken,
+        right: &Expression,
+    ) -> CrustCoreResult<String> {
+        self.visit_binary(left, op, right)
+    }
+
+    fn visit_path(&self, segments: &[Symbol], _line: usize) -> CrustCoreResult<String> {
+        Ok(print_path(segments))
+    }
+
+    fn visit_unary(&self, op: &SourceToken, right: &Expression) -> CrustCoreResult<String> {
+        let res = format!("{} {}", right.accept(self)?, operator(op));
+        Ok(res)
+    }
+
+    fn visit_variable(&self, name: Symbol, _line: usize) -> CrustCoreResult<String> {
+        Ok(name.to_string())
+    }
+}
+
+/// Prints the tree back as Crust source, the source mode of [`AstPrinter`]. Groupings in the
+/// tree are ignored and parentheses are put back only where precedence needs them, so
+/// `((1))` prints as `1` and `(1 + 2) * 3` keeps its parentheses while `1 + (2 * 3)` loses
+/// them.
+pub struct SourcePrinter;
+
+const ASSIGNMENT: u8 = 1;
+const CONDITIONAL: u8 = 2;
+const COALESCE: u8 = 3;
+const OR: u8 = 4;
+const AND: u8 = 5;
+const EQUALITY: u8 = 6;
+const COMPARISON: u8 = 7;
+const TERM: u8 = 8;
+const FACTOR: u8 = 9;
+const UNARY: u8 = 10;
+const POWER: u8 = 11;
+const CALL: u8 = 12;
+const PRIMARY: u8 = 13;
+
+/// How tightly `expr` binds, following the parser's precedence levels.
+fn precedence(expr: &Expression) -> u8 {
+    match expr {
+        Expression::Assign { .. } => ASSIGNMENT,
+        Expression::Conditional { .. } => CONDITIONAL,
+        Expression::Binary { op, .. } | Expression::Logical { op, .. } => binding(&op.token),
+        Expression::Unary { .. } => UNARY,
+        // A folded negative number prints with its `-`, which binds like a unary minus.
+        Expression::Literal { value } => match value.token {
+            Token::Integer(n) if n < 0 => UNARY,
+            Token::Float(n) if n.is_sign_negative() => UNARY,
+            _ => PRIMARY,
+        },
+        Expression::Call { .. } | Expression::Index { .. } => CALL,
+        Expression::Grouping { expr } => precedence(expr),
+        Expression::Block { .. }
+        | Expression::Interpolation { .. }
+        | Expression::Lambda { .. }
+        | Expression::Path { .. }
+        | Expression::Variable { .. } => PRIMARY,
+    }
+}
+
+/// `printed`, the printed form of `expr`, in parentheses when `expr` binds more loosely than
+/// `min`.
+fn wrap(printed: String, expr: &Expression, min: u8) -> String {
+    if precedence(expr) < min {
+        format!("({printed})")
+    } else {
+        printed
+    }
+}
+
+/// How tightly a binary or logical operator binds.
+fn binding(op: &Token) -> u8 {
+    match op {
+        Token::QuestionQuestion => COALESCE,
+        Token::Or => OR,
+        Token::And => AND,
+        Token::Greater | Token::GreaterEqual | Token::Less | Token::LessEqual => COMPARISON,
+        Token::Minus | Token::Plus => TERM,
+        Token::Slash | Token::Star | Token::Percent => FACTOR,
+        Token::StarStar => POWER,
+        _ => EQUALITY,
+    }
+}
+
+impl SourcePrinter {
+    /// Prints `expr` where the parser expects an operand binding at least as tightly as `min`,
+    /// wrapping it in parentheses when it binds more loosely.
+    fn operand(&self, expr: &Expression, min: u8) -> CrustCoreResult<String> {
+        Ok(wrap(expr.accept(self)?, expr, min))
+    }
+
+    /// Prints `op` between `left`, already printed as `printed`, and `right`.
+    fn join(
+        &self,
+        printed: String,
+        left: &Expression,
+        op: &SourceToken,
+        right: &Expression,
+    ) -> CrustCoreResult<String> {
+        let level = binding(&op.token);
+        // `**` groups to the right and takes a unary operand on its right only.
+        let (left_min, right_min) = match op.token {
+            Token::StarStar => (level + 1, UNARY),
+            _ => (level, level + 1),
+        };
+        let res = format!(
+            "{} {} {}",
+            wrap(printed, left, left_min),
+            operator(op),
+            self.operand(right, right_min)?
+        );
+        Ok(res)
+    }
+
+    fn print_block(
+        &self,
+        statements: &[Statement],
+        value: Option<&Expression>,
+    ) -> CrustCoreResult<String> {
+        let mut parts = statements
+            .iter()
+            .map(|statement| statement.accept(self))
+            .collect::<CrustCoreResult<Vec<String>>>()?;
+        if let Some(value) = value {
+            parts.push(value.accept(self)?);
+        }
+        if parts.is_empty() {
+            Ok("{ }".to_string())
+        } else {
+            Ok(format!("{{ {} }}", parts.join(" ")))
+        }
+    }
+}
+
+impl Visitor<String> for SourcePrinter {
+    fn visit_assign(
+        &self,
+        name: Symbol,
+        _line: usize,
+        value: &Expression,
+    ) -> CrustCoreResult<String> {
+        let res = format!("{} = {}", name, self.operand(value, ASSIGNMENT)?);
+        Ok(res)
+    }
+
+    /// Binary operators associate to the left, so a right operand at the same level, as in
+    /// `a - (b - c)`, needs its parentheses.
+    fn visit_binary(
+        &self,
+        left: &Expression,
+        op: &SourceToken,
+        right: &Expression,
+    ) -> CrustCoreResult<String> {
+        let (first, chain) = left.chain();
+        let mut res = first.accept(self)?;
+        let mut inner = first;
+        for (node, op, right) in chain {
+            res = self.join(res, inner, op, right)?;
+            inner = node;
+        }
+        self.join(res, inner, op, right)
+    }
+
+    fn visit_block_expression(
+        &self,
+        statements: &[Statement],
+        value: Option<&Expression>,
+        _line: usize,
+    ) -> CrustCoreResult<String> {
+        self.print_block(statements, value)
+    }
+
+    fn visit_call(
+        &self,
+        callee: &Expression,
+        _line: usize,
+        args: &[Expression],
+    ) -> CrustCoreResult<String> {
+        let args = args
+            .iter()
+            .map(|arg| self.operand(arg, ASSIGNMENT))
+            .collect::<CrustCoreResult<Vec<String>>>()?;
+        let res = format!("{}({})", self.operand(callee, CALL)?, args.join(", "));
+        Ok(res)
+    }
+
+    fn visit_conditional(
+        &self,
+        condition: &Expression,
+        then_branch: &Expression,
+        else_branch: &Expression,
+    ) -> CrustCoreResult<String> {
+        let res = format!(
+            "{} ? {} : {}",
+            self.operand(condition, COALESCE)?,
+            self.operand(then_branch, ASSIGNMENT)?,
+            self.operand(else_branch, CONDITIONAL)?
+        );
+        Ok(res)
+    }
+
+    fn visit_grouping(&self, expr: &Expression) -> CrustCoreResult<String> {
+        expr.accept(self)
+    }
+
+    fn visit_index(
+        &self,
+        target: &Expression,
+        index: &Expression,
+        _line: usize,
+    ) -> CrustCoreResult<String> {
+        let res = format!(
+            "{}[{}]",
+            self.operand(target, CALL)?,
+            self.operand(index, ASSIGNMENT)?
+        );
+        Ok(res)
+    }
+
+    /// Text parts are written back with their escapes, including `\$` for a `$` that would
+    /// otherwise start an embedded expression.
+    fn visit_interpolation(&self, parts: &[Expression], _line: usize) -> CrustCoreResult<String> {
+        let mut res = "\"".to_string();
+        for part in parts {
+            match part {
+                Expression::Literal {
+                    value:
+                        SourceToken {
+                            token: Token::String(text),
+                            ..
+                        },
+                } => {
+                    let quoted = format!("{text:?}");
+                    res.push_str(&quoted[1..quoted.len() - 1].replace("${", "\\${"));
+                }
+                part => res.push_str(&format!("${{{}}}", part.accept(self)?)),
+            }
+        }
+        res.push('"');
+        Ok(res)
+    }
+
+    fn visit_lambda(
+        &self,
+        params: &[Parameter],
+        body: &[Statement],
+        _line: usize,
+    ) -> CrustCoreResult<String> {
+        let params = params.iter().map(parameter).collect::<Vec<String>>();
+        let res = format!(
+            "fn({}) {}",
+            params.join(", "),
+            self.print_block(body, None)?
+        );
+        Ok(res)
+    }
+
+    fn visit_literal(&self, value: &SourceToken) -> CrustCoreResult<String> {
+        AstPrinter {}.visit_literal(value)
+    }
+
+    fn visit_logical(
+        &self,
+        left: &Expression,
+        op: &SourceToken,
+        right: &Expression,
+    ) -> CrustCoreResult<String> {
+        self.visit_binary(left, op, right)
+    }
+
+    fn visit_path(&self, segments: &[Symbol], _line: usize) -> CrustCoreResult<String> {
+        Ok(print_path(segments))
+    }
+
+    fn visit_unary(&self, op: &SourceToken, right: &Expression) -> CrustCoreResult<String> {
+        let op = operator(op);
+        let right = self.operand(right, UNARY)?;
+        // `- -x` must not run together into the `--` operator.
+        if (op == "-" || op == "+") && right.starts_with(&op) {
+            Ok(format!("{op} {right}"))
+        } else {
+            Ok(format!("{op}{right}"))
+        }
+    }
+
+    fn visit_variable(&self, name: Symbol, _line: usize) -> CrustCoreResult<String> {
+        Ok(name.to_string())
+    }
+}
+
+impl StatementVisitor<String> for SourcePrinter {
+    fn visit_block(&self, statements: &[Statement]) -> CrustCoreResult<String> {
+        self.print_block(statements, None)
+    }
+
+    fn visit_expression(&self, expr: &Expression) -> CrustCoreResult<String> {
+        let res = format!("{};", expr.accept(self)?);
+        Ok(res)
+    }
+
+    fn visit_for(
+        &self,
+        name: Symbol,
+        start: &Expression,
+        end: &Expression,
+        inclusive: bool,
+        body: &[Statement],
+    ) -> CrustCoreResult<String> {
+        let res = format!(
+            "for {} in {}{}{} {}",
+            name,
+            start.accept(self)?,
+            if inclusive { "..=" } else { ".." },
+            end.accept(self)?,
+            self.print_block(body, None)?
+        );
+        Ok(res)
+    }
+
+    fn visit_function(
+        &self,
+        name: Symbol,
+        params: &[Parameter],
+        return_type: Option<&str>,
+        body: &[Statement],
+    ) -> CrustCoreResult<String> {
+        let params = params.iter().map(parameter).collect::<Vec<String>>();
+        let return_type = match return_type {
+            Some(return_type) => format!(" -> {}", return_type),
+            None => String::new(),
+        };
+        let res = format!(
+            "fn {}({}){} {}",
+            name,
+            params.join(", "),
+            return_type,
+            self.print_block(body, None)?
+        );
+        Ok(res)
+    }
+
+    fn visit_const(&self, name: Symbol, initializer: &Expression) -> CrustCoreResult<String> {
+        let res = format!("const {} = {};", name, initializer.accept(self)?);
+        Ok(res)
+    }
+
+    fn visit_let(
+        &self,
+        name: Symbol,
+        mutable: bool,
+        annotation: Option<&str>,
+        initializer: Option<&Expression>,
+    ) -> CrustCoreResult<String> {
+        let mut res = if mutable {
+            format!("let mut {}", name)
+        } else {
+            format!("let {}", name)
+        };
+        if let Some(annotation) = annotation {
+            res.push_str(&format!(": {annotation}"));
+        }
+        if let Some(initializer) = initializer {
+            res.push_str(&format!(" = {}", initializer.accept(self)?));
+        }
+        res.push(';');
+        Ok(res)
+    }
+
+    fn visit_print(&self, expr: &Expression) -> CrustCoreResult<String> {
+        let res = format!("print {};", expr.accept(self)?);
+        Ok(res)
+    }
+
+    fn visit_return(&self, value: Option<&Expression>) -> CrustCoreResult<String> {
+        let res = match value {
+            Some(value) => format!("return {};", value.accept(self)?),
+            None => "return;".to_string(),
+        };
         Ok(res)
     }
 }
@@ -86,26 +1173,137 @@ impl Visitor<String> for AstPrinter {
 mod tests {
     use super::*;
 
+    fn token(token: Token<'static>) -> SourceToken {
+        SourceToken::new(token, "", 0, 1)
+    }
+
     #[test]
     fn print_ast() {
         let expr = Expression::Binary {
             left: Box::new(Expression::Unary {
-                op: Token::Minus,
+                op: token(Token::Minus),
                 right: Box::new(Expression::Literal {
-                    value: Token::Float(2.0),
+                    value: token(Token::Float(2.0)),
                 }),
             }),
-            op: Token::Star,
+            op: token(Token::Star),
             right: Box::new(Expression::Grouping {
                 expr: Box::new(Expression::Literal {
-                    value: Token::Integer(15),
+                    value: token(Token::Integer(15)),
                 }),
             }),
         };
         let visitor = AstPrinter {};
         assert_eq!(
             expr.accept(&visitor).unwrap(),
-            "( Star ( Minus 2.0 ) ( group 15 ) )"
+            "( * ( - 2.0 ) ( group 15 ) )"
         );
     }
+
+    #[test]
+    fn print_ast_built_with_helpers() {
+        let expr = Expression::binary(
+            Expression::unary(
+                token(Token::Minus),
+                Expression::literal(token(Token::Float(2.0))),
+            ),
+            token(Token::Star),
+            Expression::grouping(Expression::literal(token(Token::Integer(15)))),
+        );
+        assert_eq!(
+            expr.accept(&AstPrinter {}).unwrap(),
+            "( * ( - 2.0 ) ( group 15 ) )"
+        );
+    }
+
+    #[test]
+    fn source_mode_adds_only_needed_parentheses() {
+        let int = |n| Expression::literal(token(Token::Integer(n)));
+        let var = |name| Expression::Variable {
+            name: Symbol::intern(name),
+            line: 1,
+        };
+        let print = |expr: Expression| expr.accept(&SourcePrinter {}).unwrap();
+        assert_eq!(
+            print(Expression::grouping(Expression::grouping(int(1)))),
+            "1"
+        );
+        let sum = Expression::binary(int(1), token(Token::Plus), int(2));
+        assert_eq!(
+            print(Expression::binary(sum.clone(), token(Token::Star), int(3))),
+            "(1 + 2) * 3"
+        );
+        let product = Expression::binary(int(2), token(Token::Star), int(3));
+        assert_eq!(
+            print(Expression::binary(
+                int(1),
+                token(Token::Plus),
+                Expression::grouping(product)
+            )),
+            "1 + 2 * 3"
+        );
+        let difference = Expression::binary(var("b"), token(Token::Minus), var("c"));
+        assert_eq!(
+            print(Expression::binary(
+                var("a"),
+                token(Token::Minus),
+                difference.clone()
+            )),
+            "a - (b - c)"
+        );
+        assert_eq!(
+            print(Expression::unary(
+                token(Token::Minus),
+                Expression::unary(token(Token::Minus), var("x"))
+            )),
+            "- -x"
+        );
+    }
+
+    #[test]
+    fn print_literals_unambiguously() {
+        let print = |value| {
+            Expression::literal(token(value))
+                .accept(&AstPrinter {})
+                .unwrap()
+        };
+        assert_eq!(print(Token::Float(2.0)), "2.0");
+        assert_eq!(print(Token::Float(2.5)), "2.5");
+        assert_eq!(print(Token::Float(1e20)), "100000000000000000000.0");
+        assert_eq!(print(Token::Integer(2)), "2");
+        assert_eq!(print(Token::String("a \"b\"".into())), "\"a \\\"b\\\"\"");
+        assert_eq!(print(Token::Char('c')), "'c'");
+        assert_eq!(print(Token::Nil), "nil");
+    }
+
+    #[test]
+    fn print_rpn() {
+        let expr = Expression::Binary {
+            left: Box::new(Expression::Grouping {
+                expr: Box::new(Expression::Binary {
+                    left: Box::new(Expression::Literal {
+                        value: token(Token::Integer(1)),
+                    }),
+                    op: token(Token::Plus),
+                    right: Box::new(Expression::Literal {
+                        value: token(Token::Integer(2)),
+                    }),
+                }),
+            }),
+            op: token(Token::Star),
+            right: Box::new(Expression::Grouping {
+                expr: Box::new(Expression::Binary {
+                    left: Box::new(Expression::Literal {
+                        value: token(Token::Integer(4)),
+                    }),
+                    op: token(Token::Minus),
+                    right: Box::new(Expression::Literal {
+                        value: token(Token::Integer(3)),
+                    }),
+                }),
+            }),
+        };
+        let visitor = RpnPrinter {};
+        assert_eq!(expr.accept(&visitor).unwrap(), "1 2 + 4 3 - *");
+    }
 }
@@ -0,0 +1,182 @@
+use crust_grammar::token::{SourceToken, Token, TokenType};
+
+use crate::util::{CrustCoreErr, CrustCoreResult, ErrorKind};
+
+/// A position in a token stream that always ends in `Eof`. Once the cursor reaches the `Eof`
+/// it stays there, so peeking and advancing past the end are safe and keep returning it.
+pub(crate) struct TokenCursor {
+    tokens: Vec<SourceToken>,
+    current: usize,
+}
+
+impl TokenCursor {
+    pub(crate) fn new(mut tokens: Vec<SourceToken>) -> Self {
+        // A stream built without an `Eof`, even an empty one, gets one.
+        if !matches!(tokens.last(), Some(last) if last.token == Token::Eof) {
+            let (offset, line) = tokens
+                .last()
+                .map_or((0, 1), |last| (last.offset + last.length, last.line));
+            tokens.push(SourceToken::new(Token::Eof, "", offset, line));
+        }
+        Self { tokens, current: 0 }
+    }
+
+    pub(crate) fn is_at_end(&self) -> bool {
+        self.peek().token == Token::Eof
+    }
+
+    pub(crate) fn peek(&self) -> &SourceToken {
+        &self.tokens[self.current]
+    }
+
+    pub(crate) fn peek_kind(&self) -> TokenType {
+        self.peek().token.kind()
+    }
+
+    /// The token `advance` last moved past, or the first token before any.
+    pub(crate) fn previous(&self) -> &SourceToken {
+        &self.tokens[self.current.saturating_sub(1)]
+    }
+
+    /// Moves past the next token and returns it. At the end it stays on `Eof` and returns that.
+    pub(crate) fn advance(&mut self) -> &SourceToken {
+        if self.is_at_end() {
+            return self.peek();
+        }
+        self.current += 1;
+        self.previous()
+    }
+
+    pub(crate) fn check(&self, token_type: TokenType) -> bool {
+        self.peek_kind() == token_type
+    }
+
+    /// Whether the token after the next one is a `token_type`, looking no further than `Eof`.
+    pub(crate) fn check_next(&self, token_type: TokenType) -> bool {
+        let next = (self.current + 1).min(self.tokens.len() - 1);
+        self.tokens[next].token.kind() == token_type
+    }
+
+    /// Advances past the next token if it is any of `token_types`.
+    pub(crate) fn matches(&mut self, token_types: &[TokenType]) -> bool {
+        if token_types.iter().any(|token_type| self.check(*token_type)) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub(crate) fn consume(
+        &mut self,
+        token_type: TokenType,
+        message: &str,
+    ) -> CrustCoreResult<&SourceToken> {
+        if self.check(token_type) {
+            Ok(self.advance())
+        } else {
+            Err(self.error(ErrorKind::Expected, message))
+        }
+    }
+
+    /// A Parse error pointing at the next token, which says so when the input ran out first.
+    pub(crate) fn error(&self, kind: ErrorKind, message: &str) -> CrustCoreErr {
+        let token = self.peek();
+        let message = if self.is_at_end() {
+            format!("Unexpected end of input: {message}")
+        } else {
+            message.to_string()
+        };
+        CrustCoreErr::Parse {
+            kind,
+            line: token.line,
+            message,
+            span: Some(token.offset..token.offset + token.length),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::Scanner;
+
+    fn cursor(source: &str) -> TokenCursor {
+        TokenCursor::new(Scanner::new(source).scan_tokens().unwrap())
+    }
+
+    #[test]
+    fn peek_and_advance() {
+        let mut cursor = cursor("let x");
+        assert_eq!(cursor.peek().token, Token::Let);
+        assert_eq!(cursor.peek_kind(), TokenType::Let);
+        assert_eq!(cursor.advance().token, Token::Let);
+        assert_eq!(cursor.previous().token, Token::Let);
+        assert_eq!(cursor.peek_kind(), TokenType::Identifier);
+        assert_eq!(cursor.advance().token, Token::Identifier("x".into()));
+        assert!(cursor.is_at_end());
+    }
+
+    #[test]
+    fn check_and_matches() {
+        let mut cursor = cursor("( 1");
+        assert!(cursor.check(TokenType::LeftParen));
+        assert!(!cursor.check(TokenType::RightParen));
+        assert!(!cursor.matches(&[TokenType::RightParen, TokenType::Comma]));
+        assert_eq!(cursor.peek_kind(), TokenType::LeftParen);
+        assert!(cursor.matches(&[TokenType::RightParen, TokenType::LeftParen]));
+        assert_eq!(cursor.peek_kind(), TokenType::Integer);
+        assert!(cursor.check_next(TokenType::Eof));
+        cursor.advance();
+        assert!(cursor.check_next(TokenType::Eof));
+    }
+
+    #[test]
+    fn consume_expected_tokens() {
+        let mut cursor = cursor("a;");
+        match cursor.consume(TokenType::Semicolon, "Expected ';'") {
+            Err(CrustCoreErr::Parse { message, span, .. }) => {
+                assert_eq!(message, "Expected ';'");
+                assert_eq!(span, Some(0..1));
+            }
+            other => panic!("Expected a Parse error, got {other:?}"),
+        }
+        assert_eq!(
+            cursor.consume(TokenType::Identifier, "").unwrap().lexeme,
+            "a"
+        );
+        assert!(cursor.consume(TokenType::Semicolon, "").is_ok());
+    }
+
+    #[test]
+    fn stays_at_eof() {
+        let mut cursor = cursor("");
+        assert!(cursor.is_at_end());
+        assert_eq!(cursor.previous().token, Token::Eof);
+        for _ in 0..3 {
+            assert_eq!(cursor.advance().token, Token::Eof);
+            assert_eq!(cursor.peek().token, Token::Eof);
+        }
+        assert!(cursor.check(TokenType::Eof));
+        assert!(cursor.consume(TokenType::Eof, "").is_ok());
+
+        let mut cursor = TokenCursor::new(vec![]);
+        assert_eq!(cursor.advance().token, Token::Eof);
+        assert!(!cursor.matches(&[TokenType::Semicolon]));
+    }
+
+    #[test]
+    fn advancing_past_the_last_token_gives_eof() {
+        let mut cursor = cursor("1 +");
+        assert_eq!(cursor.advance().token, Token::Integer(1));
+        assert_eq!(cursor.advance().token, Token::Plus);
+        for _ in 0..3 {
+            assert_eq!(cursor.advance().token, Token::Eof);
+            assert_eq!(cursor.previous().token, Token::Plus);
+        }
+        assert_eq!(
+            cursor.consume(TokenType::Eof, "").unwrap().token,
+            Token::Eof
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc, sync::OnceLock};
+
+use crust_grammar::symbol::Symbol;
+
+use crate::{interpreter::Value, util::edit_distance};
+
+/// Why an assignment was rejected, left for the interpreter to report with a source position.
+#[derive(Debug, PartialEq)]
+pub enum AssignError {
+    Undefined,
+    Immutable,
+    Constant,
+}
+
+/// Whether `name` is `_`, which may be declared and assigned any number of times but never
+/// holds a value, so that it can never be read.
+pub fn is_discard(name: Symbol) -> bool {
+    static DISCARD: OnceLock<Symbol> = OnceLock::new();
+    name == *DISCARD.get_or_init(|| Symbol::intern("_"))
+}
+
+struct Binding {
+    value: Value,
+    mutable: bool,
+    constant: bool,
+}
+
+/// A single lexical scope. Scopes are shared through `Rc<RefCell<_>>` so that functions can
+/// keep the scope they were declared in alive as their closure.
+#[derive(Default)]
+pub struct Environment {
+    values: HashMap<Symbol, Binding>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn new_enclosed(enclosing: Rc<RefCell<Environment>>) -> Self {
+        Self {
+            values: HashMap::new(),
+            enclosing: Some(enclosing),
+        }
+    }
+
+    pub fn define(&mut self, name: Symbol, value: Value, mutable: bool) {
+        if is_discard(name) {
+            return;
+        }
+        let binding = Binding {
+            value,
+            mutable,
+            constant: false,
+        };
+        self.values.insert(name, binding);
+    }
+
+    pub fn define_const(&mut self, name: Symbol, value: Value) {
+        if is_discard(name) {
+            return;
+        }
+        let binding = Binding {
+            value,
+            mutable: false,
+            constant: true,
+        };
+        self.values.insert(name, binding);
+    }
+
+    pub fn get(&self, name: Symbol) -> Option<Value> {
+        if let Some(binding) = self.values.get(&name) {
+            Some(binding.value.clone())
+        } else if let Some(enclosing) = &self.enclosing {
+            enclosing.borrow().get(name)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the nearest binding for `name` is `let mut`, or `None` if it is undefined.
+    pub fn is_mutable(&self, name: Symbol) -> Option<bool> {
+        if let Some(binding) = self.values.get(&name) {
+            Some(binding.mutable)
+        } else if let Some(enclosing) = &self.enclosing {
+            enclosing.borrow().is_mutable(name)
+        } else {
+            None
+        }
+    }
+
+    pub fn assign(&mut self, name: Symbol, value: Value) -> Result<(), AssignError> {
+        if is_discard(name) {
+            return Ok(());
+        }
+        if let Some(binding) = self.values.get_mut(&name) {
+            if binding.constant {
+                return Err(AssignError::Constant);
+            } else if !binding.mutable {
+                return Err(AssignError::Immutable);
+            }
+            binding.value = value;
+            Ok(())
+        } else if let Some(enclosing) = &self.enclosing {
+            enclosing.borrow_mut().assign(name, value)
+        } else {
+            Err(AssignError::Undefined)
+        }
+    }
+
+    /// The visible name most similar to `name`, if one is within an edit distance of 2 that is
+    /// also less than the length of `name`, so that `b` is not "corrected" to `a`. Ties go to the
+    /// alphabetically first name so that suggestions do not depend on hash order.
+    pub fn closest_name(&self, name: Symbol) -> Option<Symbol> {
+        let limit = name.as_str().chars().count().min(3);
+        let mut names = vec![];
+        self.collect_names(&mut names);
+        names
+            .into_iter()
+            .map(|candidate| (edit_distance(name.as_str(), candidate.as_str()), candidate))
+            .filter(|(distance, _)| *distance < limit)
+            .min_by_key(|(distance, candidate)| (*distance, candidate.as_str()))
+            .map(|(_, candidate)| candidate)
+    }
+
+    fn collect_names(&self, names: &mut Vec<Symbol>) {
+        names.extend(self.values.keys());
+        if let Some(enclosing) = &self.enclosing {
+            enclosing.borrow().collect_names(names);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_from_enclosing_scope() {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        globals
+            .borrow_mut()
+            .define("a".into(), Value::Integer(1), false);
+        let local = Environment::new_enclosed(globals);
+
+        assert_eq!(local.get("a".into()), Some(Value::Integer(1)));
+        assert_eq!(local.get("b".into()), None);
+    }
+
+    #[test]
+    fn assign_respects_mutability() {
+        let mut environment = Environment::new();
+        environment.define("a".into(), Value::Integer(1), true);
+        environment.define("b".into(), Value::Integer(2), false);
+
+        environment.assign("a".into(), Value::Integer(3)).unwrap();
+        assert_eq!(environment.get("a".into()), Some(Value::Integer(3)));
+        assert_eq!(
+            environment.assign("b".into(), Value::Integer(4)),
+            Err(AssignError::Immutable)
+        );
+        assert_eq!(
+            environment.assign("c".into(), Value::Integer(5)),
+            Err(AssignError::Undefined)
+        );
+    }
+}
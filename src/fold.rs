@@ -0,0 +1,417 @@
+use crust_grammar::{
+    symbol::Symbol,
+    token::{SourceToken, Token},
+};
+
+use crate::{
+    ast::{Expression, Parameter, Statement, Visitor},
+    interpreter::{binary, unary, Value},
+    util::CrustCoreResult,
+};
+
+/// Folds constant arithmetic in every expression of `program`, e.g. `2 * 3 + 4` becomes the
+/// literal `10`.
+pub(crate) fn fold_program(program: Vec<Statement>) -> Vec<Statement> {
+    FOLD_CONSTANTS.program(program)
+}
+
+pub(crate) fn fold(expr: &Expression) -> Expression {
+    FOLD_CONSTANTS.expression(expr)
+}
+
+/// Removes every `Grouping` from `expr`. The shape of the tree already says how operands
+/// group, so they only matter for printing, where
+/// [`SourcePrinter`](crate::ast::SourcePrinter) puts back the parentheses that are needed.
+pub fn strip_groupings(expr: &Expression) -> Expression {
+    STRIP_GROUPINGS.expression(expr)
+}
+
+const FOLD_CONSTANTS: Rewriter = Rewriter {
+    fold_constants: true,
+    strip_groupings: false,
+};
+
+const STRIP_GROUPINGS: Rewriter = Rewriter {
+    fold_constants: false,
+    strip_groupings: true,
+};
+
+/// Rebuilds an expression bottom-up. When folding constants, `Binary` and `Unary` nodes whose
+/// operands are number literals are replaced with the literal they evaluate to. The operators
+/// are applied with the interpreter's own rules, and a node that would fail, e.g. by dividing
+/// by zero or overflowing, is left as it is so that the error is still reported when it runs.
+struct Rewriter {
+    fold_constants: bool,
+    strip_groupings: bool,
+}
+
+impl Rewriter {
+    fn program(&self, program: Vec<Statement>) -> Vec<Statement> {
+        program
+            .into_iter()
+            .map(|statement| self.statement(statement))
+            .collect()
+    }
+
+    fn expression(&self, expr: &Expression) -> Expression {
+        expr.accept(self).expect("rewriting never fails")
+    }
+
+    fn boxed(&self, expr: &Expression) -> CrustCoreResult<Box<Expression>> {
+        Ok(Box::new(expr.accept(self)?))
+    }
+
+    /// Rewrites the left operand of a binary or logical operator, looping over the operators
+    /// chained in it rather than recursing into each of their left operands.
+    fn chain(&self, left: &Expression) -> CrustCoreResult<Expression> {
+        let (first, chain) = left.chain();
+        let mut expr = first.accept(self)?;
+        for (node, op, right) in chain {
+            let right = right.accept(self)?;
+            expr = match node {
+                Expression::Logical { .. } => Expression::logical(expr, op.clone(), right),
+                _ => self.binary(expr, op, right),
+            };
+        }
+        Ok(expr)
+    }
+
+    /// `left op right`, or the literal it evaluates to when folding constants.
+    fn binary(&self, left: Expression, op: &SourceToken, right: Expression) -> Expression {
+        if let (true, Some(l), Some(r)) = (self.fold_constants, number(&left), number(&right)) {
+            if let Some(folded) = literal(binary(l, op, r), op) {
+                return folded;
+            }
+        }
+        Expression::binary(left, op.clone(), right)
+    }
+
+    fn statement(&self, statement: Statement) -> Statement {
+        let fold = |expr: &Expression| self.expression(expr);
+        match statement {
+            Statement::Block { statements, line } => Statement::Block {
+                statements: self.program(statements),
+                line,
+            },
+            Statement::Expression { expr } => Statement::Expression { expr: fold(&expr) },
+            Statement::For {
+                name,
+                start,
+                end,
+                inclusive,
+                body,
+                line,
+            } => Statement::For {
+                name,
+                start: fold(&start),
+                end: fold(&end),
+                inclusive,
+                body: self.program(body),
+                line,
+            },
+            Statement::Function {
+                name,
+                params,
+                return_type,
+                body,
+                line,
+            } => Statement::Function {
+                name,
+                params,
+                return_type,
+                body: self.program(body),
+                line,
+            },
+            Statement::Const {
+                name,
+                initializer,
+                line,
+            } => Statement::Const {
+                name,
+                initializer: fold(&initializer),
+                line,
+            },
+            Statement::Let {
+                name,
+                mutable,
+                annotation,
+                initializer,
+                line,
+            } => Statement::Let {
+                name,
+                mutable,
+                annotation,
+                initializer: initializer.as_ref().map(fold),
+                line,
+            },
+            Statement::Print { expr } => Statement::Print { expr: fold(&expr) },
+            Statement::Return { value, line } => Statement::Return {
+                value: value.as_ref().map(fold),
+                line,
+            },
+        }
+    }
+}
+
+fn number(expr: &Expression) -> Option<Value> {
+    match expr {
+        Expression::Literal { value } => match value.token {
+            Token::Integer(value) => Some(Value::Integer(value)),
+            Token::Float(value) => Some(Value::Float(value)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The literal for a folded number, placed at the operator it replaces.
+fn literal(value: CrustCoreResult<Value>, op: &SourceToken) -> Option<Expression> {
+    let (token, lexeme) = match value {
+        Ok(value @ Value::Integer(number)) => (Token::Integer(number), value.to_string()),
+        Ok(value @ Value::Float(number)) => (Token::Float(number), value.to_string()),
+        _ => return None,
+    };
+    Some(Expression::Literal {
+        value: SourceToken::new(token, &lexeme, op.offset, op.line),
+    })
+}
+
+impl Visitor<Expression> for Rewriter {
+    fn visit_assign(
+        &self,
+        name: Symbol,
+        line: usize,
+        value: &Expression,
+    ) -> CrustCoreResult<Expression> {
+        Ok(Expression::Assign {
+            name,
+            line,
+            value: self.boxed(value)?,
+        })
+    }
+
+    fn visit_binary(
+        &self,
+        left: &Expression,
+        op: &SourceToken,
+        right: &Expression,
+    ) -> CrustCoreResult<Expression> {
+        let left = self.chain(left)?;
+        let right = right.accept(self)?;
+        Ok(self.binary(left, op, right))
+    }
+
+    fn visit_block_expression(
+        &self,
+        statements: &[Statement],
+        value: Option<&Expression>,
+        line: usize,
+    ) -> CrustCoreResult<Expression> {
+        Ok(Expression::Block {
+            statements: self.program(statements.to_vec()),
+            value: value.map(|value| self.boxed(value)).transpose()?,
+            line,
+        })
+    }
+
+    fn visit_call(
+        &self,
+        callee: &Expression,
+        line: usize,
+        args: &[Expression],
+    ) -> CrustCoreResult<Expression> {
+        Ok(Expression::Call {
+            callee: self.boxed(callee)?,
+            line,
+            args: args
+                .iter()
+                .map(|arg| arg.accept(self))
+                .collect::<CrustCoreResult<_>>()?,
+        })
+    }
+
+    fn visit_conditional(
+        &self,
+        condition: &Expression,
+        then_branch: &Expression,
+        else_branch: &Expression,
+    ) -> CrustCoreResult<Expression> {
+        Ok(Expression::Conditional {
+            condition: self.boxed(condition)?,
+            then_branch: self.boxed(then_branch)?,
+            else_branch: self.boxed(else_branch)?,
+        })
+    }
+
+    fn visit_grouping(&self, expr: &Expression) -> CrustCoreResult<Expression> {
+        let expr = expr.accept(self)?;
+        if self.strip_groupings || (self.fold_constants && number(&expr).is_some()) {
+            return Ok(expr);
+        }
+        Ok(Expression::Grouping {
+            expr: Box::new(expr),
+        })
+    }
+
+    fn visit_index(
+        &self,
+        target: &Expression,
+        index: &Expression,
+        line: usize,
+    ) -> CrustCoreResult<Expression> {
+        Ok(Expression::Index {
+            target: self.boxed(target)?,
+            index: self.boxed(index)?,
+            line,
+        })
+    }
+
+    fn visit_interpolation(
+        &self,
+        parts: &[Expression],
+        line: usize,
+    ) -> CrustCoreResult<Expression> {
+        Ok(Expression::Interpolation {
+            parts: parts
+                .iter()
+                .map(|part| part.accept(self))
+                .collect::<CrustCoreResult<_>>()?,
+            line,
+        })
+    }
+
+    fn visit_lambda(
+        &self,
+        params: &[Parameter],
+        body: &[Statement],
+        line: usize,
+    ) -> CrustCoreResult<Expression> {
+        Ok(Expression::Lambda {
+            params: params.to_vec(),
+            body: self.program(body.to_vec()),
+            line,
+        })
+    }
+
+    fn visit_literal(&self, value: &SourceToken) -> CrustCoreResult<Expression> {
+        Ok(Expression::Literal {
+            value: value.clone(),
+        })
+    }
+
+    fn visit_logical(
+        &self,
+        left: &Expression,
+        op: &SourceToken,
+        right: &Expression,
+    ) -> CrustCoreResult<Expression> {
+        Ok(Expression::logical(
+            self.chain(left)?,
+            op.clone(),
+            right.accept(self)?,
+        ))
+    }
+
+    fn visit_path(&self, segments: &[Symbol], line: usize) -> CrustCoreResult<Expression> {
+        Ok(Expression::Path {
+            segments: segments.to_vec(),
+            line,
+        })
+    }
+
+    fn visit_unary(&self, op: &SourceToken, right: &Expression) -> CrustCoreResult<Expression> {
+        let right = right.accept(self)?;
+        if let (true, Some(value)) = (self.fold_constants, number(&right)) {
+            if let Some(folded) = literal(unary(op, value), op) {
+                return Ok(folded);
+            }
+        }
+        Ok(Expression::Unary {
+            op: op.clone(),
+            right: Box::new(right),
+        })
+    }
+
+    fn visit_variable(&self, name: Symbol, line: usize) -> CrustCoreResult<Expression> {
+        Ok(Expression::Variable { name, line })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast::{AstPrinter, SourcePrinter},
+        interpreter::Interpreter,
+        parser::Parser,
+        scanner::Scanner,
+    };
+
+    fn folded(source: &str) -> Expression {
+        let tokens = Scanner::new(&format!("{source};")).scan_tokens().unwrap();
+        match Parser::new(tokens).parse_program().unwrap().as_slice() {
+            [Statement::Expression { expr }] => expr.clone(),
+            other => panic!("Expected one expression statement, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn folds_constant_arithmetic() {
+        match folded("2 * 3 + 4") {
+            Expression::Literal { value } => assert_eq!(value.token, Token::Integer(10)),
+            other => panic!("Expected a literal, found {other:?}"),
+        }
+        assert_eq!(
+            folded("-(1.5 * 2.0) + x").accept(&AstPrinter {}).unwrap(),
+            "( + -3.0 x )"
+        );
+    }
+
+    #[test]
+    fn stripped_groupings_still_evaluate() {
+        let one = SourceToken::new(Token::Integer(1), "1", 2, 1);
+        let expr = strip_groupings(&Expression::grouping(Expression::grouping(
+            Expression::literal(one),
+        )));
+        assert!(matches!(expr, Expression::Literal { .. }));
+        assert_eq!(expr.accept(&SourcePrinter {}).unwrap(), "1");
+
+        let expr = strip_groupings(&folded("(x + 1) * ((x))"));
+        assert_eq!(expr.accept(&SourcePrinter {}).unwrap(), "(x + 1) * x");
+        let tokens = Scanner::new("let x = 2;").scan_tokens().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+        let value = Interpreter::new()
+            .interpret_with_value(&program, Some(&expr))
+            .unwrap();
+        assert_eq!(value, Value::Integer(6));
+    }
+
+    #[test]
+    fn conditionals_print_back_with_needed_parentheses() {
+        let print = |source| {
+            strip_groupings(&folded(source))
+                .accept(&SourcePrinter {})
+                .unwrap()
+        };
+        for (input, printed) in [
+            ("(a ? b : c) ? d : e", "(a ? b : c) ? d : e"),
+            ("a ? (b ? c : d) : e", "a ? b ? c : d : e"),
+            ("a ? b : (c ? d : e)", "a ? b : c ? d : e"),
+            ("x = (a ? b : c)", "x = a ? b : c"),
+            ("(x = a) ? b : c", "(x = a) ? b : c"),
+            ("a ? b : (c = d)", "a ? b : (c = d)"),
+            ("(a ?? b) ? c : d", "a ?? b ? c : d"),
+        ] {
+            assert_eq!(print(input), printed, "{input}");
+            assert_eq!(print(printed), printed, "{printed}");
+        }
+    }
+
+    #[test]
+    fn leaves_variables_and_failures_unfolded() {
+        let expr = folded("x + 1");
+        assert!(matches!(expr, Expression::Binary { .. }));
+        assert_eq!(expr.accept(&AstPrinter {}).unwrap(), "( + x 1 )");
+        assert_eq!(folded("1 / 0").accept(&AstPrinter {}).unwrap(), "( / 1 0 )");
+    }
+}
@@ -0,0 +1,2036 @@
+use std::{
+    cell::{Cell, RefCell},
+    fmt::{self, Debug, Display},
+    io::{self, BufRead, BufReader, Read, Write},
+    rc::Rc,
+    time::Instant,
+};
+
+use crust_grammar::{
+    symbol::Symbol,
+    token::{SourceToken, Token},
+};
+
+use crate::{
+    ast::{print_path, Expression, Parameter, Statement, StatementVisitor, Visitor},
+    environment::{is_discard, AssignError, Environment},
+    natives::define_natives,
+    util::{CrustCoreErr, CrustCoreResult, ErrorKind},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Integer(i64),
+    Float(f64),
+    Str(String),
+    Bool(bool),
+    Function(Rc<Function>),
+    NativeFunction(Rc<NativeFunction>),
+    Nil,
+}
+
+/// A user-defined function together with the scope it was declared in. The scope is captured
+/// by reference rather than copied, so the function sees later assignments to the variables in
+/// it, and its own assignments to them are seen by everything else sharing the scope, e.g.
+/// another call of the same closure. It also sees names declared in that scope after it.
+///
+/// A function is usually defined in the scope it captures, and that reference cycle keeps both
+/// alive until the program ends.
+pub struct Function {
+    name: Symbol,
+    params: Vec<Symbol>,
+    body: Vec<Statement>,
+    closure: Rc<RefCell<Environment>>,
+}
+
+impl Debug for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fn {}>", self.name)
+    }
+}
+
+/// Functions compare by identity, two separately declared functions are never equal.
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+/// The Rust side of a native function: it receives the interpreter, the evaluated arguments and
+/// the line of the call.
+pub type NativeFn = dyn Fn(&Interpreter, &[Value], usize) -> CrustCoreResult<Value>;
+
+/// Where `read_line()` gets its lines from: the process's stdin by default, or canned input
+/// given to [`Interpreter::with_input`].
+pub trait ReadLine {
+    /// Appends the next line, with its line ending, to `buf` like [`BufRead::read_line`],
+    /// returning the number of bytes read, which is 0 at the end of the input.
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize>;
+}
+
+/// Reads through the stdin handle shared with the rest of the process, so that a script
+/// reading lines at the REPL takes only the lines it asks for.
+impl ReadLine for io::Stdin {
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        io::Stdin::read_line(self, buf)
+    }
+}
+
+impl<T: AsRef<[u8]>> ReadLine for io::Cursor<T> {
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        BufRead::read_line(self, buf)
+    }
+}
+
+impl<R: Read> ReadLine for BufReader<R> {
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        BufRead::read_line(self, buf)
+    }
+}
+
+/// A function implemented in Rust, such as `clock`.
+pub struct NativeFunction {
+    pub(crate) name: &'static str,
+    pub(crate) arity: usize,
+    pub(crate) function: Box<NativeFn>,
+}
+
+impl Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+impl PartialEq for NativeFunction {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+/// How control leaves a statement, so that `return` can unwind through enclosing blocks.
+pub enum Flow {
+    Normal,
+    Return(Value),
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Integer(_) => "Integer",
+            Value::Float(_) => "Float",
+            Value::Str(_) => "Str",
+            Value::Bool(_) => "Bool",
+            Value::Function(_) => "Function",
+            Value::NativeFunction(_) => "NativeFunction",
+            Value::Nil => "Nil",
+        }
+    }
+
+    /// The truth value of a `Value`: `Bool` maps to itself, `Nil` is false and every other
+    /// value, including `0`, `0.0` and `""`, is true.
+    ///
+    /// The interpreter itself is strict: boolean contexts such as `!`, `and` and `or` only
+    /// accept `Bool` operands and report a Runtime error for anything else, so this is only
+    /// ever consulted for explicit bools there.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Bool(value) => *value,
+            Value::Nil => false,
+            _ => true,
+        }
+    }
+}
+
+/// The form `print` uses: strings without quotes, `true`/`false`, `nil`, and `<fn name>` for
+/// functions. Whole floats keep a trailing `.0`, so `2.0` prints as `2.0` and is never
+/// mistaken for the Integer `2`. Negative zero keeps its sign and prints as `-0.0`, though it
+/// compares equal to `0.0`.
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Integer(value) => write!(f, "{value}"),
+            Value::Float(value) if value.is_finite() && value.fract() == 0.0 => {
+                write!(f, "{value:.1}")
+            }
+            Value::Float(value) => write!(f, "{value}"),
+            Value::Str(value) => write!(f, "{value}"),
+            Value::Bool(value) => write!(f, "{value}"),
+            Value::Function(function) => write!(f, "{function:?}"),
+            Value::NativeFunction(function) => write!(f, "{function:?}"),
+            Value::Nil => write!(f, "nil"),
+        }
+    }
+}
+
+pub struct Interpreter {
+    output: RefCell<Box<dyn Write>>,
+    input: RefCell<Box<dyn ReadLine>>,
+    environment: RefCell<Rc<RefCell<Environment>>>,
+    clock: Box<dyn Fn() -> f64>,
+    float_epsilon: Option<f64>,
+    /// How many function calls are running, each of which recurses through the tree walk.
+    depth: Cell<usize>,
+    max_depth: usize,
+    /// How many expressions and blocks the tree walk is inside, across all the running calls.
+    nesting: Cell<usize>,
+    max_nesting: usize,
+}
+
+/// The deepest nesting of function calls an [`Interpreter`] allows by default. Expressions
+/// are already limited by the parser, so calls are what recursion can grow without bound, as
+/// in `fn f() { return f(); }`.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 200;
+
+/// How many expressions and blocks the tree walk of an [`Interpreter`] may be inside at once
+/// by default, counting those of every running call. A few calls with deeply nested bodies
+/// stay under [`DEFAULT_MAX_CALL_DEPTH`] but can still recurse further than the stack allows,
+/// so this keeps the walk inside the 8 MiB stack of a main thread even in debug builds. A
+/// smaller stack, like the 2 MiB of a spawned thread, needs a lower
+/// [`Interpreter::with_max_nesting`].
+pub const DEFAULT_MAX_NESTING: usize = 2000;
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self::with_output(Box::new(io::stdout()))
+    }
+
+    /// An interpreter whose `print` writes to `output` rather than stdout, e.g. a buffer that
+    /// tests read back.
+    pub fn with_output(output: Box<dyn Write>) -> Self {
+        let mut globals = Environment::new();
+        define_natives(&mut globals);
+        let start = Instant::now();
+        Self {
+            output: RefCell::new(output),
+            input: RefCell::new(Box::new(io::stdin())),
+            environment: RefCell::new(Rc::new(RefCell::new(globals))),
+            clock: Box::new(move || start.elapsed().as_secs_f64()),
+            float_epsilon: None,
+            depth: Cell::new(0),
+            max_depth: DEFAULT_MAX_CALL_DEPTH,
+            nesting: Cell::new(0),
+            max_nesting: DEFAULT_MAX_NESTING,
+        }
+    }
+
+    /// Replaces stdin as the source of the lines `read_line()` returns.
+    pub fn with_input(mut self, input: Box<dyn ReadLine>) -> Self {
+        self.input = RefCell::new(input);
+        self
+    }
+
+    /// Limits how deeply function calls may nest. A deeper call is a "Maximum nesting depth
+    /// exceeded" Runtime error rather than a stack overflow.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Limits how many expressions and blocks the tree walk may be inside at once, across all
+    /// the running calls. Going deeper is a "Maximum nesting depth exceeded" Runtime error
+    /// rather than a stack overflow.
+    pub fn with_max_nesting(mut self, max_nesting: usize) -> Self {
+        self.max_nesting = max_nesting;
+        self
+    }
+
+    /// Makes `==` and `!=` treat two Floats as equal when they differ by at most `epsilon`, so
+    /// that `0.1 + 0.2 == 0.3` holds. Only comparisons of a Float with a Float are affected:
+    /// Integers, mixed operands and the ordering operators `<`, `>`, `<=` and `>=` still compare
+    /// exactly, and `NaN` is never equal to anything.
+    pub fn with_float_epsilon(mut self, epsilon: f64) -> Self {
+        self.float_epsilon = Some(epsilon);
+        self
+    }
+
+    /// Replaces the time source behind `clock()`, which otherwise counts seconds since the
+    /// interpreter was created.
+    #[cfg(test)]
+    pub fn with_clock(mut self, clock: impl Fn() -> f64 + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    pub(crate) fn now(&self) -> f64 {
+        (self.clock)()
+    }
+
+    pub(crate) fn write_line(&self, value: &Value, line: usize) -> CrustCoreResult {
+        writeln!(self.output.borrow_mut(), "{value}").map_err(|err| {
+            runtime_error(
+                ErrorKind::InvalidOperation,
+                line,
+                format!("Failed to write output: {err}"),
+            )
+        })
+    }
+
+    /// The next line of input without its line ending, or `Nil` at the end of the input.
+    pub(crate) fn read_line(&self, line: usize) -> CrustCoreResult<Value> {
+        let mut text = String::new();
+        let read = self
+            .input
+            .borrow_mut()
+            .read_line(&mut text)
+            .map_err(|err| {
+                runtime_error(
+                    ErrorKind::InvalidOperation,
+                    line,
+                    format!("Failed to read input: {err}"),
+                )
+            })?;
+        if read == 0 {
+            return Ok(Value::Nil);
+        }
+        if text.ends_with('\n') {
+            text.pop();
+            if text.ends_with('\r') {
+                text.pop();
+            }
+        }
+        Ok(Value::Str(text))
+    }
+
+    pub fn interpret(&self, statements: &[Statement]) -> CrustCoreResult {
+        self.execute_all(statements)?;
+        Ok(())
+    }
+
+    pub fn evaluate(&self, expression: &Expression) -> CrustCoreResult<Value> {
+        self.deepen(expression.line())?;
+        let value = expression.accept(self);
+        self.nesting.set(self.nesting.get() - 1);
+        value
+    }
+
+    /// Runs `statements`, then evaluates `value`. Without a separate `value`, a final
+    /// expression statement provides it instead, and a program ending in anything else is `nil`.
+    pub fn interpret_with_value(
+        &self,
+        statements: &[Statement],
+        value: Option<&Expression>,
+    ) -> CrustCoreResult<Value> {
+        let (statements, value) = match (statements, value) {
+            ([rest @ .., Statement::Expression { expr }], None) => (rest, Some(expr)),
+            other => other,
+        };
+        self.interpret(statements)?;
+        value.map_or(Ok(Value::Nil), |value| self.evaluate(value))
+    }
+
+    fn execute_all(&self, statements: &[Statement]) -> CrustCoreResult<Flow> {
+        for statement in statements {
+            if let Flow::Return(value) = statement.accept(self)? {
+                return Ok(Flow::Return(value));
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    /// Runs `statements` with `environment` as the current scope, restoring the previous scope
+    /// afterwards even if execution fails.
+    fn execute_block(
+        &self,
+        statements: &[Statement],
+        environment: Environment,
+    ) -> CrustCoreResult<Flow> {
+        self.deepen(statements.first().map_or(0, Statement::line))?;
+        let flow = self.in_scope(environment, || self.execute_all(statements));
+        self.nesting.set(self.nesting.get() - 1);
+        flow
+    }
+
+    /// Goes one level deeper into the tree, unless that would pass `max_nesting`.
+    fn deepen(&self, line: usize) -> CrustCoreResult<()> {
+        if self.nesting.get() >= self.max_nesting {
+            return Err(runtime_error(
+                ErrorKind::NestingTooDeep,
+                line,
+                "Maximum nesting depth exceeded".to_string(),
+            ));
+        }
+        self.nesting.set(self.nesting.get() + 1);
+        Ok(())
+    }
+
+    /// Runs `run` with `environment` as the current scope, restoring the previous scope
+    /// afterwards even if it fails.
+    fn in_scope<T>(
+        &self,
+        environment: Environment,
+        run: impl FnOnce() -> CrustCoreResult<T>,
+    ) -> CrustCoreResult<T> {
+        let previous = self.environment.replace(Rc::new(RefCell::new(environment)));
+        let result = run();
+        self.environment.replace(previous);
+        result
+    }
+
+    fn current_scope(&self) -> Rc<RefCell<Environment>> {
+        self.environment.borrow().clone()
+    }
+
+    /// Calls `callee`, a user-defined or native function, with `args`, for natives that take
+    /// functions as well as for call expressions.
+    pub(crate) fn call(
+        &self,
+        callee: Value,
+        args: Vec<Value>,
+        line: usize,
+    ) -> CrustCoreResult<Value> {
+        match callee {
+            Value::Function(function) => self.call_function(&function, args, line),
+            Value::NativeFunction(native) => {
+                if args.len() != native.arity {
+                    return Err(runtime_error(
+                        ErrorKind::WrongArgumentCount,
+                        line,
+                        format!(
+                            "Expected {} arguments but got {} when calling '{}'",
+                            native.arity,
+                            args.len(),
+                            native.name
+                        ),
+                    ));
+                }
+                (native.function)(self, &args, line)
+            }
+            other => Err(runtime_error(
+                ErrorKind::InvalidOperation,
+                line,
+                format!("Can only call functions, found {}", other.type_name()),
+            )),
+        }
+    }
+
+    fn call_function(
+        &self,
+        function: &Function,
+        args: Vec<Value>,
+        line: usize,
+    ) -> CrustCoreResult<Value> {
+        if args.len() != function.params.len() {
+            return Err(runtime_error(
+                ErrorKind::WrongArgumentCount,
+                line,
+                format!(
+                    "Expected {} arguments but got {} when calling '{}'",
+                    function.params.len(),
+                    args.len(),
+                    function.name
+                ),
+            ));
+        }
+
+        if self.depth.get() >= self.max_depth {
+            return Err(runtime_error(
+                ErrorKind::CallsTooDeep,
+                line,
+                "Maximum nesting depth exceeded".to_string(),
+            ));
+        }
+
+        let mut environment = Environment::new_enclosed(function.closure.clone());
+        for (param, arg) in function.params.iter().zip(args) {
+            environment.define(*param, arg, false);
+        }
+
+        self.depth.set(self.depth.get() + 1);
+        let flow = self.execute_block(&function.body, environment);
+        self.depth.set(self.depth.get() - 1);
+        match flow? {
+            Flow::Return(value) => Ok(value),
+            Flow::Normal => Ok(Value::Nil),
+        }
+    }
+
+    /// Rejects a `const` initializer that reads a `let mut` variable or assigns to anything,
+    /// since its value could then differ from one run of the declaration to the next.
+    fn check_constant(&self, expr: &Expression) -> CrustCoreResult {
+        match expr {
+            Expression::Assign { name, line, .. } => Err(runtime_error(
+                ErrorKind::InvalidOperation,
+                *line,
+                format!("Constant initializer cannot assign to '{name}'"),
+            )),
+            Expression::Variable { name, line } => {
+                if self.current_scope().borrow().is_mutable(*name) == Some(true) {
+                    Err(runtime_error(
+                        ErrorKind::InvalidOperation,
+                        *line,
+                        format!("Constant initializer cannot read mutable variable '{name}'"),
+                    ))
+                } else {
+                    Ok(())
+                }
+            }
+            Expression::Binary { left, right, .. } | Expression::Logical { left, right, .. } => {
+                self.check_constant(left)?;
+                self.check_constant(right)
+            }
+            Expression::Index { target, index, .. } => {
+                self.check_constant(target)?;
+                self.check_constant(index)
+            }
+            Expression::Call { callee, args, .. } => {
+                self.check_constant(callee)?;
+                args.iter().try_for_each(|arg| self.check_constant(arg))
+            }
+            Expression::Interpolation { parts, .. } => {
+                parts.iter().try_for_each(|part| self.check_constant(part))
+            }
+            Expression::Conditional {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.check_constant(condition)?;
+                self.check_constant(then_branch)?;
+                self.check_constant(else_branch)
+            }
+            Expression::Grouping { expr } | Expression::Unary { right: expr, .. } => {
+                self.check_constant(expr)
+            }
+            Expression::Block { line, .. } => Err(runtime_error(
+                ErrorKind::InvalidOperation,
+                *line,
+                "Constant initializer cannot contain a block".to_string(),
+            )),
+            Expression::Path { segments, line } if segments.len() == 1 => {
+                self.check_constant(&Expression::Variable {
+                    name: segments[0],
+                    line: *line,
+                })
+            }
+            // A lambda's body only runs when it is called, not as part of the initializer.
+            Expression::Lambda { .. } | Expression::Literal { .. } | Expression::Path { .. } => {
+                Ok(())
+            }
+        }
+    }
+
+    /// Reports `name` as undefined, suggesting a similarly spelled name that is in scope.
+    fn undefined_variable(&self, name: Symbol, line: usize) -> CrustCoreErr {
+        let message = match self.current_scope().borrow().closest_name(name) {
+            Some(suggestion) => {
+                format!("Undefined variable '{name}'. Did you mean '{suggestion}'?")
+            }
+            None => format!("Undefined variable '{name}'"),
+        };
+        runtime_error(ErrorKind::UndefinedVariable, line, message)
+    }
+
+    fn expect_bool(&self, value: Value, op: &SourceToken) -> CrustCoreResult<bool> {
+        match value {
+            Value::Bool(value) => Ok(value),
+            other => Err(runtime_error(
+                ErrorKind::InvalidOperation,
+                op.line,
+                format!(
+                    "Expected Bool operand for '{}', found {}",
+                    op.lexeme,
+                    other.type_name()
+                ),
+            )),
+        }
+    }
+
+    /// Evaluates the left operand of a binary or logical operator, looping over the operators
+    /// chained in it rather than recursing into each of their left operands.
+    fn evaluate_chain(&self, left: &Expression) -> CrustCoreResult<Value> {
+        let (first, chain) = left.chain();
+        let mut value = self.evaluate(first)?;
+        for (node, op, right) in chain {
+            value = match node {
+                Expression::Logical { .. } => self.apply_logical(value, op, right)?,
+                _ => {
+                    let right = self.evaluate(right)?;
+                    self.apply_binary(value, op, right)?
+                }
+            };
+        }
+        Ok(value)
+    }
+
+    /// Applies a binary operator to operands that are already evaluated.
+    fn apply_binary(&self, left: Value, op: &SourceToken, right: Value) -> CrustCoreResult<Value> {
+        if let (Some(epsilon), Value::Float(l), Value::Float(r)) =
+            (self.float_epsilon, &left, &right)
+        {
+            let equal = l == r || (l - r).abs() <= epsilon;
+            match op.token {
+                Token::EqualEqual => return Ok(Value::Bool(equal)),
+                Token::BangEqual => return Ok(Value::Bool(!equal)),
+                _ => {}
+            }
+        }
+        binary(left, op, right)
+    }
+
+    /// Applies a logical operator to an evaluated left operand, evaluating `right` only when
+    /// `left` does not already decide the result.
+    fn apply_logical(
+        &self,
+        left: Value,
+        op: &SourceToken,
+        right: &Expression,
+    ) -> CrustCoreResult<Value> {
+        if op.token == Token::QuestionQuestion {
+            return match left {
+                Value::Nil => self.evaluate(right),
+                left => Ok(left),
+            };
+        }
+
+        let left = self.expect_bool(left, op)?;
+        match &op.token {
+            Token::Or if left => Ok(Value::Bool(true)),
+            Token::And if !left => Ok(Value::Bool(false)),
+            Token::Or | Token::And => Ok(Value::Bool(self.expect_bool(self.evaluate(right)?, op)?)),
+            other => Err(runtime_error(
+                ErrorKind::InvalidOperation,
+                op.line,
+                format!("Unsupported logical operator {:?}", other),
+            )),
+        }
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub(crate) fn runtime_error(kind: ErrorKind, line: usize, message: String) -> CrustCoreErr {
+    CrustCoreErr::Runtime {
+        kind,
+        line,
+        message,
+        span: None,
+    }
+}
+
+/// Points a Runtime error raised while applying `op` at the operator itself.
+fn at_operator(err: CrustCoreErr, op: &SourceToken) -> CrustCoreErr {
+    match err {
+        CrustCoreErr::Runtime {
+            kind,
+            line,
+            message,
+            span: None,
+        } => CrustCoreErr::Runtime {
+            kind,
+            line,
+            message,
+            span: Some(op.offset..op.offset + op.length),
+        },
+        err => err,
+    }
+}
+
+fn integer_overflow(line: usize) -> CrustCoreErr {
+    runtime_error(
+        ErrorKind::IntegerOverflow,
+        line,
+        "Integer overflow".to_string(),
+    )
+}
+
+/// Adds two numbers of the same kind or concatenates two strings. Integers and floats are
+/// never promoted implicitly, so mixing them is an error like any other type mismatch.
+fn add(left: Value, right: Value, line: usize) -> CrustCoreResult<Value> {
+    match (left, right) {
+        (Value::Integer(left), Value::Integer(right)) => left
+            .checked_add(right)
+            .map(Value::Integer)
+            .ok_or_else(|| integer_overflow(line)),
+        (Value::Float(left), Value::Float(right)) => Ok(Value::Float(left + right)),
+        (Value::Str(left), Value::Str(right)) => Ok(Value::Str(left + &right)),
+        (left, right) => Err(runtime_error(
+            ErrorKind::InvalidOperation,
+            line,
+            format!("Cannot add {} and {}", left.type_name(), right.type_name()),
+        )),
+    }
+}
+
+/// The longest string, in bytes, that repeating a string may build.
+const MAX_REPEATED_LENGTH: usize = 1 << 28;
+
+/// `"ab" * 3`, in either operand order, repeats a string. A result longer than
+/// [`MAX_REPEATED_LENGTH`] is a Runtime error rather than an allocation failure.
+fn repeat(text: &str, count: i64, line: usize) -> CrustCoreResult<Value> {
+    let Ok(count) = usize::try_from(count) else {
+        return Err(runtime_error(
+            ErrorKind::InvalidOperation,
+            line,
+            format!("Cannot repeat a string {count} times"),
+        ));
+    };
+    match text.len().checked_mul(count) {
+        Some(length) if length <= MAX_REPEATED_LENGTH => Ok(Value::Str(text.repeat(count))),
+        _ => Err(runtime_error(
+            ErrorKind::InvalidOperation,
+            line,
+            format!("Repeating a string {count} times makes it too long"),
+        )),
+    }
+}
+
+/// Applies `-`, `*`, `/`, `%` or `**` to two numbers of the same kind. Integer arithmetic is
+/// checked, so overflow and a zero divisor are Runtime errors rather than panics, while floats
+/// follow IEEE 754 and divide by zero to an infinity or NaN. As in Rust, `/` on Integers
+/// truncates towards zero and `%` takes the sign of the dividend, so `-7 % 3` is `-1`.
+fn arithmetic(left: Value, op: &SourceToken, right: Value) -> CrustCoreResult<Value> {
+    match (left, right) {
+        (Value::Integer(_), Value::Integer(0))
+            if matches!(op.token, Token::Slash | Token::Percent) =>
+        {
+            Err(runtime_error(
+                ErrorKind::DivisionByZero,
+                op.line,
+                "Division by zero".to_string(),
+            ))
+        }
+        (Value::Integer(_), Value::Integer(exponent))
+            if op.token == Token::StarStar && exponent < 0 =>
+        {
+            Err(runtime_error(
+                ErrorKind::InvalidOperation,
+                op.line,
+                format!("Cannot raise an Integer to the negative power {exponent}, use a Float"),
+            ))
+        }
+        (Value::Integer(left), Value::Integer(right)) => match op.token {
+            Token::Minus => left.checked_sub(right),
+            Token::StarStar => u32::try_from(right)
+                .ok()
+                .and_then(|exponent| left.checked_pow(exponent)),
+            Token::Star => left.checked_mul(right),
+            Token::Slash => left.checked_div(right),
+            _ => left.checked_rem(right),
+        }
+        .map(Value::Integer)
+        .ok_or_else(|| integer_overflow(op.line)),
+        (Value::Float(left), Value::Float(right)) => Ok(Value::Float(match op.token {
+            Token::Minus => left - right,
+            Token::Star => left * right,
+            Token::Slash => left / right,
+            Token::StarStar => left.powf(right),
+            _ => left % right,
+        })),
+        (Value::Str(text), Value::Integer(count)) | (Value::Integer(count), Value::Str(text))
+            if op.token == Token::Star =>
+        {
+            repeat(&text, count, op.line)
+        }
+        (left, right) => Err(runtime_error(
+            ErrorKind::InvalidOperation,
+            op.line,
+            format!(
+                "Cannot apply '{}' to {} and {}",
+                op.token.symbol().unwrap_or_default(),
+                left.type_name(),
+                right.type_name()
+            ),
+        )),
+    }
+}
+
+/// Applies a binary operator to two evaluated operands.
+pub(crate) fn binary(left: Value, op: &SourceToken, right: Value) -> CrustCoreResult<Value> {
+    let result = match &op.token {
+        Token::Plus => add(left, right, op.line),
+        Token::Minus | Token::Star | Token::StarStar | Token::Slash | Token::Percent => {
+            arithmetic(left, op, right)
+        }
+        Token::Greater | Token::GreaterEqual | Token::Less | Token::LessEqual => {
+            compare(left, op, right)
+        }
+        Token::EqualEqual => Ok(Value::Bool(left == right)),
+        Token::BangEqual => Ok(Value::Bool(left != right)),
+        other => Err(runtime_error(
+            ErrorKind::InvalidOperation,
+            op.line,
+            format!("Unsupported binary operator {:?}", other),
+        )),
+    };
+    result.map_err(|err| at_operator(err, op))
+}
+
+/// Applies a unary operator to an evaluated operand.
+pub(crate) fn unary(op: &SourceToken, right: Value) -> CrustCoreResult<Value> {
+    let result = match (&op.token, right) {
+        (Token::Bang, right @ Value::Bool(_)) => Ok(Value::Bool(!right.is_truthy())),
+        (Token::Bang, other) => Err(runtime_error(
+            ErrorKind::InvalidOperation,
+            op.line,
+            format!("Expected Bool operand for !, found {}", other.type_name()),
+        )),
+        (Token::Minus, Value::Integer(value)) => value
+            .checked_neg()
+            .map(Value::Integer)
+            .ok_or_else(|| integer_overflow(op.line)),
+        (Token::Minus, Value::Float(value)) => Ok(Value::Float(-value)),
+        (Token::Minus, other) => Err(runtime_error(
+            ErrorKind::InvalidOperation,
+            op.line,
+            format!("Cannot negate {}", other.type_name()),
+        )),
+        (Token::Plus, value @ (Value::Integer(_) | Value::Float(_))) => Ok(value),
+        (Token::Plus, other) => Err(runtime_error(
+            ErrorKind::InvalidOperation,
+            op.line,
+            format!("Expected a number for unary +, found {}", other.type_name()),
+        )),
+        (other, _) => Err(runtime_error(
+            ErrorKind::InvalidOperation,
+            op.line,
+            format!("Unsupported unary operator {:?}", other),
+        )),
+    };
+    result.map_err(|err| at_operator(err, op))
+}
+
+/// Orders two numbers of the same kind with a comparison operator. As with `add` there is no
+/// promotion, so comparing an Integer with a Float is a type error.
+fn compare(left: Value, op: &SourceToken, right: Value) -> CrustCoreResult<Value> {
+    fn ordered<T: PartialOrd>(op: &Token, left: T, right: T) -> bool {
+        match op {
+            Token::Greater => left > right,
+            Token::GreaterEqual => left >= right,
+            Token::Less => left < right,
+            _ => left <= right,
+        }
+    }
+
+    match (left, right) {
+        (Value::Integer(left), Value::Integer(right)) => {
+            Ok(Value::Bool(ordered(&op.token, left, right)))
+        }
+        (Value::Float(left), Value::Float(right)) => {
+            Ok(Value::Bool(ordered(&op.token, left, right)))
+        }
+        (left, right) => Err(runtime_error(
+            ErrorKind::InvalidOperation,
+            op.line,
+            format!(
+                "Cannot compare {} and {}",
+                left.type_name(),
+                right.type_name()
+            ),
+        )),
+    }
+}
+
+impl StatementVisitor<Flow> for Interpreter {
+    fn visit_block(&self, statements: &[Statement]) -> CrustCoreResult<Flow> {
+        self.execute_block(statements, Environment::new_enclosed(self.current_scope()))
+    }
+
+    fn visit_expression(&self, expr: &Expression) -> CrustCoreResult<Flow> {
+        self.evaluate(expr)?;
+        Ok(Flow::Normal)
+    }
+
+    /// Runs `body` once per Integer in the range, each time in a fresh scope holding an
+    /// immutable binding of the loop variable, so closures capture that iteration's value.
+    fn visit_for(
+        &self,
+        name: Symbol,
+        start: &Expression,
+        end: &Expression,
+        inclusive: bool,
+        body: &[Statement],
+    ) -> CrustCoreResult<Flow> {
+        let bound = |expr: &Expression| match self.evaluate(expr)? {
+            Value::Integer(value) => Ok(value),
+            other => Err(runtime_error(
+                ErrorKind::InvalidOperation,
+                expr.line(),
+                format!("Range bounds must be Integers, found {}", other.type_name()),
+            )),
+        };
+        let (start, end) = (bound(start)?, bound(end)?);
+        let values: Box<dyn Iterator<Item = i64>> = if inclusive {
+            Box::new(start..=end)
+        } else {
+            Box::new(start..end)
+        };
+
+        for value in values {
+            let mut environment = Environment::new_enclosed(self.current_scope());
+            environment.define(name, Value::Integer(value), false);
+            if let Flow::Return(value) = self.execute_block(body, environment)? {
+                return Ok(Flow::Return(value));
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn visit_function(
+        &self,
+        name: Symbol,
+        params: &[Parameter],
+        _return_type: Option<&str>,
+        body: &[Statement],
+    ) -> CrustCoreResult<Flow> {
+        let function = Function {
+            name,
+            params: params.iter().map(|param| param.name).collect(),
+            body: body.to_vec(),
+            closure: self.current_scope(),
+        };
+        self.current_scope()
+            .borrow_mut()
+            .define(name, Value::Function(Rc::new(function)), false);
+        Ok(Flow::Normal)
+    }
+
+    fn visit_const(&self, name: Symbol, initializer: &Expression) -> CrustCoreResult<Flow> {
+        self.check_constant(initializer)?;
+        let value = self.evaluate(initializer)?;
+        self.current_scope().borrow_mut().define_const(name, value);
+        Ok(Flow::Normal)
+    }
+
+    fn visit_let(
+        &self,
+        name: Symbol,
+        mutable: bool,
+        _annotation: Option<&str>,
+        initializer: Option<&Expression>,
+    ) -> CrustCoreResult<Flow> {
+        let value = match initializer {
+            Some(initializer) => self.evaluate(initializer)?,
+            None => Value::Nil,
+        };
+        self.current_scope()
+            .borrow_mut()
+            .define(name, value, mutable);
+        Ok(Flow::Normal)
+    }
+
+    fn visit_print(&self, expr: &Expression) -> CrustCoreResult<Flow> {
+        let value = self.evaluate(expr)?;
+        self.write_line(&value, expr.line())?;
+        Ok(Flow::Normal)
+    }
+
+    fn visit_return(&self, value: Option<&Expression>) -> CrustCoreResult<Flow> {
+        let value = match value {
+            Some(value) => self.evaluate(value)?,
+            None => Value::Nil,
+        };
+        Ok(Flow::Return(value))
+    }
+}
+
+impl Visitor<Value> for Interpreter {
+    fn visit_assign(
+        &self,
+        name: Symbol,
+        line: usize,
+        value: &Expression,
+    ) -> CrustCoreResult<Value> {
+        let value = self.evaluate(value)?;
+        let assigned = self
+            .current_scope()
+            .borrow_mut()
+            .assign(name, value.clone());
+        match assigned {
+            Ok(()) => Ok(value),
+            Err(AssignError::Undefined) => Err(self.undefined_variable(name, line)),
+            Err(AssignError::Immutable) => Err(runtime_error(
+                ErrorKind::ImmutableAssignment,
+                line,
+                format!("Cannot assign twice to immutable variable '{name}'"),
+            )),
+            Err(AssignError::Constant) => Err(runtime_error(
+                ErrorKind::ImmutableAssignment,
+                line,
+                format!("Cannot assign to constant '{name}'"),
+            )),
+        }
+    }
+
+    /// The left operand is evaluated by [`evaluate_chain`](Interpreter::evaluate_chain), so
+    /// that a chain like `a + b + c` does not nest the tree walk once per operator.
+    fn visit_binary(
+        &self,
+        left: &Expression,
+        op: &SourceToken,
+        right: &Expression,
+    ) -> CrustCoreResult<Value> {
+        let left = self.evaluate_chain(left)?;
+        let right = self.evaluate(right)?;
+        self.apply_binary(left, op, right)
+    }
+
+    /// Runs the statements in a scope of their own, then evaluates the trailing value there.
+    /// `return` cannot unwind out of an expression, so it is a Runtime error here.
+    fn visit_block_expression(
+        &self,
+        statements: &[Statement],
+        value: Option<&Expression>,
+        line: usize,
+    ) -> CrustCoreResult<Value> {
+        let environment = Environment::new_enclosed(self.current_scope());
+        self.in_scope(environment, || {
+            for statement in statements {
+                if let Flow::Return(value) = statement.accept(self)? {
+                    return Err(runtime_error(
+                        ErrorKind::InvalidOperation,
+                        line,
+                        format!("Cannot return {value} from inside a block expression"),
+                    ));
+                }
+            }
+            match value {
+                Some(value) => self.evaluate(value),
+                None => Ok(Value::Nil),
+            }
+        })
+    }
+
+    fn visit_call(
+        &self,
+        callee: &Expression,
+        line: usize,
+        args: &[Expression],
+    ) -> CrustCoreResult<Value> {
+        let callee = self.evaluate(callee)?;
+        let args = args
+            .iter()
+            .map(|arg| self.evaluate(arg))
+            .collect::<CrustCoreResult<Vec<Value>>>()?;
+        self.call(callee, args, line)
+    }
+
+    fn visit_conditional(
+        &self,
+        condition: &Expression,
+        then_branch: &Expression,
+        else_branch: &Expression,
+    ) -> CrustCoreResult<Value> {
+        match self.evaluate(condition)? {
+            Value::Bool(true) => self.evaluate(then_branch),
+            Value::Bool(false) => self.evaluate(else_branch),
+            other => Err(runtime_error(
+                ErrorKind::InvalidOperation,
+                condition.line(),
+                format!("Expected Bool condition, found {}", other.type_name()),
+            )),
+        }
+    }
+
+    fn visit_grouping(&self, expr: &Expression) -> CrustCoreResult<Value> {
+        self.evaluate(expr)
+    }
+
+    fn visit_index(
+        &self,
+        target: &Expression,
+        index: &Expression,
+        line: usize,
+    ) -> CrustCoreResult<Value> {
+        let target = self.evaluate(target)?;
+        let index = self.evaluate(index)?;
+        match (target, index) {
+            (Value::Str(text), Value::Integer(index)) => usize::try_from(index)
+                .ok()
+                .and_then(|index| text.chars().nth(index))
+                .map(|char| Value::Str(char.to_string()))
+                .ok_or_else(|| {
+                    runtime_error(
+                        ErrorKind::InvalidOperation,
+                        line,
+                        format!(
+                            "Index {index} out of bounds for string of length {}",
+                            text.chars().count()
+                        ),
+                    )
+                }),
+            (Value::Str(_), index) => Err(runtime_error(
+                ErrorKind::InvalidOperation,
+                line,
+                format!(
+                    "String index must be an Integer, found {}",
+                    index.type_name()
+                ),
+            )),
+            (target, _) => Err(runtime_error(
+                ErrorKind::InvalidOperation,
+                line,
+                format!("Cannot index into {}", target.type_name()),
+            )),
+        }
+    }
+
+    /// Joins the parts, each written as `print` would show it.
+    fn visit_interpolation(&self, parts: &[Expression], _line: usize) -> CrustCoreResult<Value> {
+        let mut res = String::new();
+        for part in parts {
+            res.push_str(&self.evaluate(part)?.to_string());
+        }
+        Ok(Value::Str(res))
+    }
+
+    fn visit_lambda(
+        &self,
+        params: &[Parameter],
+        body: &[Statement],
+        _line: usize,
+    ) -> CrustCoreResult<Value> {
+        let function = Function {
+            name: Symbol::intern("lambda"),
+            params: params.iter().map(|param| param.name).collect(),
+            body: body.to_vec(),
+            closure: self.current_scope(),
+        };
+        Ok(Value::Function(Rc::new(function)))
+    }
+
+    fn visit_literal(&self, value: &SourceToken) -> CrustCoreResult<Value> {
+        match &value.token {
+            Token::Integer(value) => Ok(Value::Integer(*value)),
+            Token::Float(value) => Ok(Value::Float(*value)),
+            Token::String(value) => Ok(Value::Str(value.to_string())),
+            Token::Char(value) => Ok(Value::Str(value.to_string())),
+            Token::True => Ok(Value::Bool(true)),
+            Token::False => Ok(Value::Bool(false)),
+            Token::Nil => Ok(Value::Nil),
+            other => Err(runtime_error(
+                ErrorKind::InvalidOperation,
+                value.line,
+                format!("Unexpected literal {:?}", other),
+            )),
+        }
+    }
+
+    /// `and` and `or` only accept `Bool` operands and always produce a `Bool`, while `??`
+    /// yields its left operand unless that is `Nil`. In every case the right operand is not
+    /// evaluated when the left one already decides the result.
+    fn visit_logical(
+        &self,
+        left: &Expression,
+        op: &SourceToken,
+        right: &Expression,
+    ) -> CrustCoreResult<Value> {
+        let left = self.evaluate_chain(left)?;
+        self.apply_logical(left, op, right)
+    }
+
+    /// There are no modules yet, so only a single-segment path, which is just a variable, can
+    /// be resolved.
+    fn visit_path(&self, segments: &[Symbol], line: usize) -> CrustCoreResult<Value> {
+        match segments {
+            [name] => self.visit_variable(*name, line),
+            _ => Err(runtime_error(
+                ErrorKind::InvalidOperation,
+                line,
+                format!("Unsupported path '{}'", print_path(segments)),
+            )),
+        }
+    }
+
+    fn visit_unary(&self, op: &SourceToken, right: &Expression) -> CrustCoreResult<Value> {
+        let right = self.evaluate(right)?;
+        unary(op, right)
+    }
+
+    fn visit_variable(&self, name: Symbol, line: usize) -> CrustCoreResult<Value> {
+        if is_discard(name) {
+            return Err(runtime_error(
+                ErrorKind::InvalidOperation,
+                line,
+                "Cannot use '_' as a value".to_string(),
+            ));
+        }
+        self.current_scope()
+            .borrow()
+            .get(name)
+            .ok_or_else(|| self.undefined_variable(name, line))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, scanner::Scanner, util::SharedBuffer};
+
+    fn parse(source: &str) -> CrustCoreResult<Vec<Statement>> {
+        let tokens = Scanner::new(source).scan_tokens()?;
+        Parser::new(tokens).parse_program()
+    }
+
+    fn evaluate_with(interpreter: &Interpreter, source: &str) -> CrustCoreResult<Value> {
+        match &parse(&format!("{source};"))?[..] {
+            [Statement::Expression { expr }] => interpreter.evaluate(expr),
+            _ => panic!("Expected a single expression statement"),
+        }
+    }
+
+    fn evaluate(source: &str) -> CrustCoreResult<Value> {
+        evaluate_with(&Interpreter::new(), source)
+    }
+
+    fn output(source: &str) -> CrustCoreResult<String> {
+        let buffer = SharedBuffer::default();
+        Interpreter::with_output(Box::new(buffer.clone())).interpret(&parse(source)?)?;
+        Ok(buffer.contents())
+    }
+
+    #[test]
+    fn evaluate_logical() {
+        assert_eq!(evaluate("true and false").unwrap(), Value::Bool(false));
+        assert_eq!(evaluate("true and true").unwrap(), Value::Bool(true));
+        assert_eq!(evaluate("false or true").unwrap(), Value::Bool(true));
+        assert_eq!(evaluate("false or false").unwrap(), Value::Bool(false));
+    }
+
+    #[test]
+    fn logical_short_circuits() {
+        // A non-Bool right operand is a type error, so these only succeed if it is skipped.
+        assert_eq!(evaluate("false and 1").unwrap(), Value::Bool(false));
+        assert_eq!(evaluate("true or \"x\"").unwrap(), Value::Bool(true));
+        assert!(matches!(
+            evaluate("true and 1"),
+            Err(CrustCoreErr::Runtime { .. })
+        ));
+        assert!(matches!(
+            evaluate("false or \"x\""),
+            Err(CrustCoreErr::Runtime { .. })
+        ));
+    }
+
+    #[test]
+    fn logical_requires_bool_left_operand() {
+        match evaluate("1 or true") {
+            Err(CrustCoreErr::Runtime { message, .. }) => {
+                assert_eq!(message, "Expected Bool operand for 'or', found Integer")
+            }
+            other => panic!("Expected a Runtime error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn evaluate_nil() {
+        assert_eq!(evaluate("nil").unwrap(), Value::Nil);
+    }
+
+    #[test]
+    fn coalesce_nil() {
+        assert_eq!(evaluate("nil ?? 5").unwrap(), Value::Integer(5));
+        let interpreter = Interpreter::new();
+        interpreter.interpret(&parse("let x;").unwrap()).unwrap();
+        assert_eq!(
+            evaluate_with(&interpreter, "x ?? 5").unwrap(),
+            Value::Integer(5)
+        );
+        // The undefined right operand would be a Runtime error if it were evaluated.
+        assert_eq!(evaluate("3 ?? undefined").unwrap(), Value::Integer(3));
+    }
+
+    #[test]
+    fn evaluate_conditional() {
+        assert_eq!(evaluate("true ? 1 : 2").unwrap(), Value::Integer(1));
+        assert_eq!(evaluate("false ? 1 : 2").unwrap(), Value::Integer(2));
+        // Only the chosen branch is evaluated.
+        assert_eq!(evaluate("true ? 1 : undefined").unwrap(), Value::Integer(1));
+        let interpreter = Interpreter::new();
+        interpreter
+            .interpret(&parse("let mut x = 0;").unwrap())
+            .unwrap();
+        evaluate_with(&interpreter, "x = false ? 1 : true ? 2 : 3").unwrap();
+        assert_eq!(evaluate_with(&interpreter, "x").unwrap(), Value::Integer(2));
+        match evaluate("nil ? 1 : 2") {
+            Err(CrustCoreErr::Runtime { message, .. }) => {
+                assert_eq!(message, "Expected Bool condition, found Nil")
+            }
+            other => panic!("Expected a Runtime error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn evaluate_bool_literals() {
+        assert_eq!(evaluate("true").unwrap(), Value::Bool(true));
+        assert_eq!(evaluate("false").unwrap(), Value::Bool(false));
+    }
+
+    #[test]
+    fn display_values() {
+        assert_eq!(output("print true; print false;").unwrap(), "true\nfalse\n");
+        assert_eq!(Value::Integer(-3).to_string(), "-3");
+        assert_eq!(Value::Float(2.0).to_string(), "2.0");
+        assert_eq!(Value::Float(2.5).to_string(), "2.5");
+        assert_eq!(Value::Float(f64::INFINITY).to_string(), "inf");
+        assert_eq!(Value::Str("a \"b\"".to_string()).to_string(), "a \"b\"");
+        assert_eq!(Value::Nil.to_string(), "nil");
+        assert_eq!(output("fn f() {} print f;").unwrap(), "<fn f>\n");
+    }
+
+    #[test]
+    fn truthiness() {
+        assert!(Value::Bool(true).is_truthy());
+        assert!(!Value::Bool(false).is_truthy());
+        assert!(!Value::Nil.is_truthy());
+        assert!(Value::Integer(0).is_truthy());
+        assert!(Value::Float(0.0).is_truthy());
+        assert!(Value::Str("".to_string()).is_truthy());
+    }
+
+    #[test]
+    fn boolean_contexts_are_strict() {
+        assert_eq!(evaluate("!false").unwrap(), Value::Bool(true));
+        assert!(matches!(evaluate("!0"), Err(CrustCoreErr::Runtime { .. })));
+        assert!(matches!(
+            evaluate("\"\" or true"),
+            Err(CrustCoreErr::Runtime { .. })
+        ));
+    }
+
+    #[test]
+    fn unary() {
+        assert_eq!(evaluate("-5").unwrap(), Value::Integer(-5));
+        assert_eq!(evaluate("-2.5").unwrap(), Value::Float(-2.5));
+        assert_eq!(evaluate("!true").unwrap(), Value::Bool(false));
+        match evaluate("-\"x\"") {
+            Err(CrustCoreErr::Runtime { message, .. }) => assert_eq!(message, "Cannot negate Str"),
+            other => panic!("Expected a Runtime error, got {other:?}"),
+        }
+        assert!(matches!(
+            evaluate("-nil"),
+            Err(CrustCoreErr::Runtime { .. })
+        ));
+        assert!(matches!(evaluate("!1"), Err(CrustCoreErr::Runtime { .. })));
+    }
+
+    #[test]
+    fn chained_unary() {
+        assert_eq!(evaluate("- -5").unwrap(), Value::Integer(5));
+        assert_eq!(evaluate("!!true").unwrap(), Value::Bool(true));
+        assert_eq!(evaluate("+2.5").unwrap(), Value::Float(2.5));
+        assert_eq!(evaluate("-+3").unwrap(), Value::Integer(-3));
+        assert!(matches!(
+            evaluate("+\"x\""),
+            Err(CrustCoreErr::Runtime { .. })
+        ));
+    }
+
+    #[test]
+    fn addition() {
+        assert_eq!(
+            evaluate("\"foo\" + \"bar\"").unwrap(),
+            Value::Str("foobar".to_string())
+        );
+        assert_eq!(evaluate("1 + 2").unwrap(), Value::Integer(3));
+        assert_eq!(evaluate("1.5 + 2.25").unwrap(), Value::Float(3.75));
+    }
+
+    #[test]
+    fn addition_type_mismatch() {
+        match evaluate("1 + \"x\"") {
+            Err(CrustCoreErr::Runtime { line, message, .. }) => {
+                assert_eq!(line, 1);
+                assert_eq!(message, "Cannot add Integer and Str")
+            }
+            other => panic!("Expected a Runtime error, got {other:?}"),
+        }
+        assert!(matches!(
+            evaluate("1 + 2.0"),
+            Err(CrustCoreErr::Runtime { .. })
+        ));
+    }
+
+    #[test]
+    fn arithmetic_operators() {
+        assert_eq!(evaluate("7 - 10").unwrap(), Value::Integer(-3));
+        assert_eq!(evaluate("6 * 7").unwrap(), Value::Integer(42));
+        assert_eq!(evaluate("7 / 2").unwrap(), Value::Integer(3));
+        assert_eq!(evaluate("7 % 3").unwrap(), Value::Integer(1));
+        assert_eq!(evaluate("1 + 2 * 3 % 4").unwrap(), Value::Integer(3));
+        assert_eq!(evaluate("5.0 / 2.0").unwrap(), Value::Float(2.5));
+        assert_eq!(
+            evaluate("3000000000 * 2").unwrap(),
+            Value::Integer(6_000_000_000)
+        );
+        assert_eq!(evaluate("0.1 + 0.2").unwrap(), Value::Float(0.1 + 0.2));
+        assert!(matches!(
+            evaluate("\"a\" - 2"),
+            Err(CrustCoreErr::Runtime { .. })
+        ));
+    }
+
+    #[test]
+    fn remainder_and_power() {
+        assert_eq!(evaluate("7 % 3").unwrap(), Value::Integer(1));
+        // The remainder has the sign of the dividend.
+        assert_eq!(evaluate("-7 % 3").unwrap(), Value::Integer(-1));
+        assert_eq!(evaluate("7 % -3").unwrap(), Value::Integer(1));
+        assert_eq!(evaluate("-7.5 % 2.0").unwrap(), Value::Float(-1.5));
+        assert_eq!(evaluate("2 ** 10").unwrap(), Value::Integer(1024));
+        assert_eq!(evaluate("2 ** 3 ** 2").unwrap(), Value::Integer(512));
+        assert_eq!(evaluate("-2 ** 2").unwrap(), Value::Integer(-4));
+        assert_eq!(evaluate("5 ** 0").unwrap(), Value::Integer(1));
+        assert_eq!(evaluate("2.0 ** 0.5").unwrap(), Value::Float(2f64.sqrt()));
+        assert_eq!(evaluate("2.0 ** -1.0").unwrap(), Value::Float(0.5));
+
+        let message = |source| match evaluate(source) {
+            Err(CrustCoreErr::Runtime { message, .. }) => message,
+            other => panic!("Expected a Runtime error, got {other:?}"),
+        };
+        assert_eq!(
+            message("2 ** -1"),
+            "Cannot raise an Integer to the negative power -1, use a Float"
+        );
+        assert_eq!(message("2 ** 63"), "Integer overflow");
+        assert_eq!(
+            message("2 ** 0.5"),
+            "Cannot apply '**' to Integer and Float"
+        );
+    }
+
+    #[test]
+    fn string_repetition() {
+        assert_eq!(
+            evaluate("\"ab\" * 3").unwrap(),
+            Value::Str("ababab".to_string())
+        );
+        assert_eq!(
+            evaluate("3 * \"ab\"").unwrap(),
+            Value::Str("ababab".to_string())
+        );
+        assert_eq!(evaluate("\"ab\" * 0").unwrap(), Value::Str(String::new()));
+        match evaluate("\"x\" * -1") {
+            Err(CrustCoreErr::Runtime { message, .. }) => {
+                assert_eq!(message, "Cannot repeat a string -1 times")
+            }
+            other => panic!("Expected a Runtime error, got {other:?}"),
+        }
+        for (source, count) in [
+            ("\"ab\" * 9223372036854775807", "9223372036854775807"),
+            ("\"ab\" * 268435456", "268435456"),
+        ] {
+            match evaluate(source) {
+                Err(CrustCoreErr::Runtime { message, .. }) => assert_eq!(
+                    message,
+                    format!("Repeating a string {count} times makes it too long")
+                ),
+                other => panic!("Expected a Runtime error, got {other:?}"),
+            }
+        }
+        // An empty string stays empty however often it is repeated.
+        assert_eq!(
+            evaluate("\"\" * 9223372036854775807").unwrap(),
+            Value::Str(String::new())
+        );
+        assert!(matches!(
+            evaluate("\"x\" / 2"),
+            Err(CrustCoreErr::Runtime { .. })
+        ));
+    }
+
+    #[test]
+    fn integer_division_by_zero() {
+        for source in ["1 / 0", "1 % 0"] {
+            match evaluate(source) {
+                Err(CrustCoreErr::Runtime { message, .. }) => {
+                    assert_eq!(message, "Division by zero")
+                }
+                other => panic!("Expected a Runtime error for {source}, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn float_division_by_zero() {
+        assert_eq!(evaluate("1.0 / 0.0").unwrap(), Value::Float(f64::INFINITY));
+        assert!(matches!(evaluate("0.0 / 0.0").unwrap(), Value::Float(value) if value.is_nan()));
+    }
+
+    #[test]
+    fn signed_zero() {
+        assert_eq!(
+            output("let z = 0.0;\nprint -0.0;\nprint -z;\nprint z * -1.0;\nprint 0.0;").unwrap(),
+            "-0.0\n-0.0\n-0.0\n0.0\n"
+        );
+        assert_eq!(evaluate("0.0 == -0.0").unwrap(), Value::Bool(true));
+        assert_eq!(evaluate("-0.0 != 0.0").unwrap(), Value::Bool(false));
+        assert_eq!(evaluate("-0.0 < 0.0").unwrap(), Value::Bool(false));
+        assert_eq!(
+            evaluate("1.0 / -0.0").unwrap(),
+            Value::Float(f64::NEG_INFINITY)
+        );
+    }
+
+    #[test]
+    fn integer_arithmetic_overflow() {
+        for source in [
+            "9223372036854775807 + 1",
+            "-9223372036854775807 - 2",
+            "4294967296 * 4294967296",
+            "-(-9223372036854775807 - 1)",
+        ] {
+            match evaluate(source) {
+                Err(CrustCoreErr::Runtime { message, .. }) => {
+                    assert_eq!(message, "Integer overflow")
+                }
+                other => panic!("Expected a Runtime error for {source}, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn comparison() {
+        assert_eq!(evaluate("3 < 5").unwrap(), Value::Bool(true));
+        assert_eq!(evaluate("5 <= 3").unwrap(), Value::Bool(false));
+        assert_eq!(evaluate("2.0 >= 2.0").unwrap(), Value::Bool(true));
+        assert_eq!(evaluate("1.5 > 2.5").unwrap(), Value::Bool(false));
+        match evaluate("\"a\" < 1") {
+            Err(CrustCoreErr::Runtime { message, .. }) => {
+                assert_eq!(message, "Cannot compare Str and Integer")
+            }
+            other => panic!("Expected a Runtime error, got {other:?}"),
+        }
+        assert!(matches!(
+            evaluate("1 < 2.0"),
+            Err(CrustCoreErr::Runtime { .. })
+        ));
+    }
+
+    #[test]
+    fn equality() {
+        assert_eq!(evaluate("1 == 1").unwrap(), Value::Bool(true));
+        assert_eq!(evaluate("\"a\" != \"b\"").unwrap(), Value::Bool(true));
+        assert_eq!(evaluate("nil == nil").unwrap(), Value::Bool(true));
+        // Values of different kinds are never equal, even when numerically the same.
+        assert_eq!(evaluate("1 == 1.0").unwrap(), Value::Bool(false));
+        assert_eq!(evaluate("nil != false").unwrap(), Value::Bool(true));
+    }
+
+    #[test]
+    fn float_equality_is_exact_unless_an_epsilon_is_set() {
+        assert_eq!(evaluate("0.1 + 0.2 == 0.3").unwrap(), Value::Bool(false));
+        let interpreter = Interpreter::new().with_float_epsilon(1e-9);
+        let evaluate = |source| evaluate_with(&interpreter, source).unwrap();
+        assert_eq!(evaluate("0.1 + 0.2 == 0.3"), Value::Bool(true));
+        assert_eq!(evaluate("0.1 + 0.2 != 0.3"), Value::Bool(false));
+        assert_eq!(evaluate("0.1 == 0.2"), Value::Bool(false));
+        assert_eq!(evaluate("1 == 1.0"), Value::Bool(false));
+    }
+
+    #[test]
+    fn unbounded_recursion_is_an_error() {
+        let source = "fn down(n) { return n == 0 ? 0 : down(n - 1); }";
+        let interpreter = Interpreter::new().with_max_depth(50);
+        interpreter.interpret(&parse(source).unwrap()).unwrap();
+        assert_eq!(
+            evaluate_with(&interpreter, "down(49)").unwrap(),
+            Value::Integer(0)
+        );
+        match evaluate_with(&interpreter, "down(50)") {
+            Err(CrustCoreErr::Runtime { message, .. }) => {
+                assert_eq!(message, "Maximum nesting depth exceeded")
+            }
+            other => panic!("Expected a Runtime error, got {other:?}"),
+        }
+        // The depth unwinds with the error, so later calls start from the top again.
+        assert_eq!(
+            evaluate_with(&interpreter, "down(10)").unwrap(),
+            Value::Integer(0)
+        );
+        assert!(output("fn f() { return f(); }\nf();").is_err());
+    }
+
+    #[test]
+    fn deep_calls_with_deep_bodies_are_an_error() {
+        // Each call nests its recursive call 20 blocks deep, so recursing well under the
+        // call depth limit still walks thousands of expressions deep.
+        let body = (0..20).fold("down(n - 1)".to_string(), |body, _| {
+            format!("{{ let a = {body}; a }}")
+        });
+        let source = format!("fn down(n) {{ return n == 0 ? 0 : {body}; }}");
+        let interpreter = Interpreter::new().with_max_nesting(300);
+        interpreter.interpret(&parse(&source).unwrap()).unwrap();
+        assert_eq!(
+            evaluate_with(&interpreter, "down(3)").unwrap(),
+            Value::Integer(0)
+        );
+        match evaluate_with(&interpreter, "down(150)") {
+            Err(CrustCoreErr::Runtime { message, .. }) => {
+                assert_eq!(message, "Maximum nesting depth exceeded")
+            }
+            other => panic!("Expected a Runtime error, got {other:?}"),
+        }
+        assert_eq!(
+            evaluate_with(&interpreter, "down(3)").unwrap(),
+            Value::Integer(0)
+        );
+
+        // The default limit fits the stack of a main thread.
+        let program = format!("{source}\nprint down(190);");
+        let result = std::thread::Builder::new()
+            .stack_size(8 << 20)
+            .spawn(move || output(&program).map_err(|err| err.to_string()))
+            .unwrap()
+            .join()
+            .unwrap();
+        assert!(result
+            .unwrap_err()
+            .contains("Maximum nesting depth exceeded"));
+    }
+
+    #[test]
+    fn call_function() {
+        let interpreter = Interpreter::new();
+        interpreter
+            .interpret(&parse("fn add(a, b) { return a + b; }").unwrap())
+            .unwrap();
+        assert_eq!(
+            evaluate_with(&interpreter, "add(2, 3)").unwrap(),
+            Value::Integer(5)
+        );
+    }
+
+    #[test]
+    fn call_annotated_function() {
+        let source = "fn add(a: Int, b: Int) -> Int { return a + b; } print add(2, 3);";
+        assert_eq!(output(source).unwrap(), "5\n");
+    }
+
+    #[test]
+    fn function_without_return_yields_nil() {
+        let interpreter = Interpreter::new();
+        interpreter.interpret(&parse("fn f() {}").unwrap()).unwrap();
+        assert_eq!(evaluate_with(&interpreter, "f()").unwrap(), Value::Nil);
+    }
+
+    #[test]
+    fn function_captures_closure() {
+        let interpreter = Interpreter::new();
+        interpreter
+            .interpret(
+                &parse("fn make_adder(n) { fn add(x) { return x + n; } return add; }").unwrap(),
+            )
+            .unwrap();
+        assert_eq!(
+            evaluate_with(&interpreter, "make_adder(2)(3)").unwrap(),
+            Value::Integer(5)
+        );
+    }
+
+    #[test]
+    fn call_with_wrong_arity() {
+        let interpreter = Interpreter::new();
+        interpreter
+            .interpret(&parse("fn add(a, b) { return a + b; }").unwrap())
+            .unwrap();
+        assert!(matches!(
+            evaluate_with(&interpreter, "add(1)"),
+            Err(CrustCoreErr::Runtime { .. })
+        ));
+    }
+
+    #[test]
+    fn constants() {
+        let interpreter = Interpreter::new();
+        interpreter
+            .interpret(&parse("let a = 2; const B = a * 3;").unwrap())
+            .unwrap();
+        assert_eq!(evaluate_with(&interpreter, "B").unwrap(), Value::Integer(6));
+        match evaluate_with(&interpreter, "B = 1") {
+            Err(CrustCoreErr::Runtime { message, .. }) => {
+                assert_eq!(message, "Cannot assign to constant 'B'")
+            }
+            other => panic!("Expected a Runtime error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn constant_initializer_must_not_read_mutable_variables() {
+        let interpreter = Interpreter::new();
+        interpreter
+            .interpret(&parse("let mut a = 1;").unwrap())
+            .unwrap();
+        match interpreter.interpret(&parse("const B = 1 + a;").unwrap()) {
+            Err(CrustCoreErr::Runtime { message, .. }) => assert_eq!(
+                message,
+                "Constant initializer cannot read mutable variable 'a'"
+            ),
+            other => panic!("Expected a Runtime error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn for_range() {
+        assert_eq!(output("for i in 0..3 { print i; }").unwrap(), "0\n1\n2\n");
+        assert_eq!(output("for i in 0..=2 { print i; }").unwrap(), "0\n1\n2\n");
+        assert_eq!(output("for i in 3..3 { print i; }").unwrap(), "");
+        // The loop variable only exists inside the body.
+        assert!(matches!(
+            output("for i in 0..1 {} print i;"),
+            Err(CrustCoreErr::Runtime { .. })
+        ));
+        assert!(matches!(
+            output("for i in 0..\"3\" {}"),
+            Err(CrustCoreErr::Runtime { .. })
+        ));
+    }
+
+    #[test]
+    fn return_from_for_loop() {
+        let source = "fn first() { for i in 5..10 { return i; } } print first();";
+        assert_eq!(output(source).unwrap(), "5\n");
+    }
+
+    #[test]
+    fn block_expression() {
+        let interpreter = Interpreter::new();
+        interpreter
+            .interpret(&parse("let x = { let a = 2; a + 1 }; let y = { x; };").unwrap())
+            .unwrap();
+        assert_eq!(evaluate_with(&interpreter, "x").unwrap(), Value::Integer(3));
+        assert_eq!(evaluate_with(&interpreter, "y").unwrap(), Value::Nil);
+        // Declarations inside the block do not leak out of it.
+        assert!(matches!(
+            evaluate_with(&interpreter, "a"),
+            Err(CrustCoreErr::Runtime { .. })
+        ));
+    }
+
+    #[test]
+    fn native_print() {
+        assert_eq!(
+            output("let result = print(\"hi\"); print result;").unwrap(),
+            "hi\nnil\n"
+        );
+        assert_eq!(output("print print;").unwrap(), "<native fn print>\n");
+        assert!(matches!(
+            output("let p = print(1, 2);"),
+            Err(CrustCoreErr::Runtime { .. })
+        ));
+    }
+
+    #[test]
+    fn postfix_increment() {
+        assert_eq!(output("let mut i = 0; i++; print i;").unwrap(), "1\n");
+        assert_eq!(
+            output("let mut i = 5; print i--; print i;").unwrap(),
+            "5\n4\n"
+        );
+        assert!(matches!(
+            output("let i = 0; i++;"),
+            Err(CrustCoreErr::Runtime { line: 1, .. })
+        ));
+        assert!(matches!(parse("1++;"), Err(CrustCoreErr::Multi { .. })));
+    }
+
+    #[test]
+    fn undefined_variable_suggestions() {
+        let message = |source| match output(source) {
+            Err(CrustCoreErr::Runtime { message, .. }) => message,
+            other => panic!("Expected a Runtime error for {source}, got {other:?}"),
+        };
+        assert_eq!(
+            message("let length = 3; fn f() { print lenght; } f();"),
+            "Undefined variable 'lenght'. Did you mean 'length'?"
+        );
+        assert_eq!(
+            message("let mut count = 0; cuont = 1;"),
+            "Undefined variable 'cuont'. Did you mean 'count'?"
+        );
+        assert_eq!(
+            message("let length = 3; print width;"),
+            "Undefined variable 'width'"
+        );
+    }
+
+    #[test]
+    fn capitalized_keywords_are_identifiers() {
+        assert_eq!(
+            evaluate_with(
+                &{
+                    let interpreter = Interpreter::with_output(Box::new(io::sink()));
+                    interpreter
+                        .interpret(&parse("let True = 1; let If = 2;").unwrap())
+                        .unwrap();
+                    interpreter
+                },
+                "True + If"
+            )
+            .unwrap(),
+            Value::Integer(3)
+        );
+    }
+
+    #[test]
+    fn native_len() {
+        assert_eq!(evaluate("len(\"héllo\")").unwrap(), Value::Integer(5));
+        assert!(matches!(
+            evaluate("len(5)"),
+            Err(CrustCoreErr::Runtime { .. })
+        ));
+    }
+
+    #[test]
+    fn string_index() {
+        assert_eq!(evaluate("\"abc\"[1]").unwrap(), Value::Str("b".to_string()));
+        assert_eq!(
+            evaluate("\"héllo\"[1]").unwrap(),
+            Value::Str("é".to_string())
+        );
+        for source in ["\"abc\"[3]", "\"abc\"[-1]", "\"abc\"[1.0]", "5[0]"] {
+            assert!(
+                matches!(evaluate(source), Err(CrustCoreErr::Runtime { line: 1, .. })),
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn native_clock() {
+        let interpreter = Interpreter::with_output(Box::new(io::sink())).with_clock(|| 1.5);
+        assert_eq!(
+            evaluate_with(&interpreter, "clock()").unwrap(),
+            Value::Float(1.5)
+        );
+        assert!(matches!(
+            evaluate_with(&interpreter, "clock(1)"),
+            Err(CrustCoreErr::Runtime { .. })
+        ));
+    }
+
+    #[test]
+    fn lambdas_are_values() {
+        assert_eq!(
+            output("let add = fn(a, b) { a + b };\nprint add(1, 2);\nprint add;").unwrap(),
+            "3\n<fn lambda>\n"
+        );
+        assert_eq!(
+            output("let offset = 10;\nprint apply(fn(x) { x + offset }, 5);").unwrap(),
+            "15\n"
+        );
+        assert_eq!(
+            output("fn twice(f, x) { return f(f(x)); }\nprint twice(fn(x) { return x * 3; }, 2);")
+                .unwrap(),
+            "18\n"
+        );
+        assert_eq!(evaluate("fn() { print 1; }()").unwrap(), Value::Nil);
+        match evaluate("apply(1, 2)") {
+            Err(CrustCoreErr::Runtime { message, .. }) => {
+                assert_eq!(message, "Can only call functions, found Integer")
+            }
+            other => panic!("Expected a Runtime error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn closures_capture_variables_not_values() {
+        let counter = "fn make_counter() {
+            let mut count = 0;
+            return fn() { count = count + 1; return count; };
+        }
+        let counter = make_counter();
+        print counter();
+        print counter();
+        print counter();
+        let other = make_counter();
+        print other();
+        print counter();";
+        assert_eq!(output(counter).unwrap(), "1\n2\n3\n1\n4\n");
+
+        let shared = "let mut x = 1;
+        let get = fn() { x };
+        fn set(value) { x = value; }
+        x = 2;
+        print get();
+        set(3);
+        print get();
+        print x;";
+        assert_eq!(output(shared).unwrap(), "2\n3\n3\n");
+    }
+
+    #[test]
+    fn native_conversions() {
+        let ok = |source| evaluate(source).unwrap();
+        assert_eq!(ok("str(42)"), Value::Str("42".to_string()));
+        assert_eq!(ok("str(2.0)"), Value::Str("2.0".to_string()));
+        assert_eq!(ok("str(nil)"), Value::Str("nil".to_string()));
+        assert_eq!(ok("int(\"7\")"), Value::Integer(7));
+        assert_eq!(ok("int(\" -7\\n\")"), Value::Integer(-7));
+        assert_eq!(ok("int(3.9)"), Value::Integer(3));
+        assert_eq!(ok("int(-3.9)"), Value::Integer(-3));
+        assert_eq!(ok("float(\"1.5\")"), Value::Float(1.5));
+        assert_eq!(ok("float(2)"), Value::Float(2.0));
+        assert_eq!(ok("int(str(12)) + 1"), Value::Integer(13));
+
+        let error = |source| match evaluate(source) {
+            Err(CrustCoreErr::Runtime { message, .. }) => message,
+            other => panic!("Expected a Runtime error for {source}, got {other:?}"),
+        };
+        assert_eq!(error("int(\"x\")"), "int cannot parse 'x' as an Integer");
+        assert_eq!(
+            error("int(\"1.5\")"),
+            "int cannot parse '1.5' as an Integer"
+        );
+        assert_eq!(
+            error("int(1.0 / 0.0)"),
+            "int cannot convert inf to an Integer"
+        );
+        assert_eq!(
+            error("int(10000000000000000000.0)"),
+            "int cannot convert 10000000000000000000 to an Integer"
+        );
+        assert_eq!(
+            error("float(\"one\")"),
+            "float cannot parse 'one' as a Float"
+        );
+        assert_eq!(
+            error("int(true)"),
+            "int expects a Str, Integer or Float, found Bool"
+        );
+        assert_eq!(
+            error("str(1, 2)"),
+            "Expected 1 arguments but got 2 when calling 'str'"
+        );
+    }
+
+    #[test]
+    fn native_read_line() {
+        let interpreter = Interpreter::with_output(Box::new(io::sink()))
+            .with_input(Box::new(io::Cursor::new("first line\nsecond\r\n")));
+        let read = || evaluate_with(&interpreter, "read_line()").unwrap();
+        assert_eq!(read(), Value::Str("first line".to_string()));
+        assert_eq!(read(), Value::Str("second".to_string()));
+        assert_eq!(read(), Value::Nil);
+        assert_eq!(read(), Value::Nil);
+    }
+
+    #[test]
+    fn variables_and_assignment() {
+        let interpreter = Interpreter::new();
+        interpreter
+            .interpret(&parse("let a = 1; let mut b = 2; { b = a + b; }").unwrap())
+            .unwrap();
+        assert_eq!(evaluate_with(&interpreter, "b").unwrap(), Value::Integer(3));
+        assert!(matches!(
+            evaluate_with(&interpreter, "a = 2"),
+            Err(CrustCoreErr::Runtime { .. })
+        ));
+    }
+
+    #[test]
+    fn only_single_segment_paths_resolve() {
+        let interpreter = Interpreter::new();
+        interpreter
+            .interpret(&parse("let a = 1;").unwrap())
+            .unwrap();
+        let path = |names: &[&str]| Expression::Path {
+            segments: names.iter().map(|name| Symbol::intern(name)).collect(),
+            line: 1,
+        };
+        assert_eq!(
+            interpreter.evaluate(&path(&["a"])).unwrap(),
+            Value::Integer(1)
+        );
+        match interpreter.evaluate(&path(&["std", "io"])) {
+            Err(CrustCoreErr::Runtime { message, .. }) => {
+                assert_eq!(message, "Unsupported path 'std::io'")
+            }
+            other => panic!("Expected a Runtime error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn print_writes_to_the_output_sink() {
+        let buffer = SharedBuffer::default();
+        let interpreter = Interpreter::with_output(Box::new(buffer.clone()));
+        interpreter
+            .interpret(&parse("print 1; print 2;").unwrap())
+            .unwrap();
+        assert_eq!(buffer.contents().as_bytes(), b"1\n2\n");
+
+        struct Closed;
+        impl Write for Closed {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed"))
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        match Interpreter::with_output(Box::new(Closed)).interpret(&parse("print 1;").unwrap()) {
+            Err(CrustCoreErr::Runtime { message, .. }) => {
+                assert_eq!(message, "Failed to write output: closed")
+            }
+            other => panic!("Expected a Runtime error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn underscore_discards_values() {
+        assert_eq!(
+            output("fn f(_, _) { return 1; }\nprint f(1, 2);").unwrap(),
+            "1\n"
+        );
+        let output = SharedBuffer::default();
+        Interpreter::with_output(Box::new(output.clone()))
+            .interpret(
+                &parse(
+                    "fn f() { print \"called\"; return 1; }\nlet _ = f();\nlet _ = 2;\n_ = 3;\nfor _ in 0..2 { print \"loop\"; }\nlet _foo = 4;\nprint _foo;",
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        assert_eq!(output.contents(), "called\nloop\nloop\n4\n");
+        match evaluate("_") {
+            Err(CrustCoreErr::Runtime { message, .. }) => {
+                assert_eq!(message, "Cannot use '_' as a value")
+            }
+            other => panic!("Expected a Runtime error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn division_by_zero_points_at_the_operator() {
+        let source = "1 +\n  2 / 0";
+        let err = evaluate(source).unwrap_err();
+        match &err {
+            CrustCoreErr::Runtime {
+                kind: ErrorKind::DivisionByZero,
+                line,
+                message,
+                span,
+            } => {
+                assert_eq!(*line, 2);
+                assert_eq!(message, "Division by zero");
+                assert_eq!(span.clone(), Some(8..9));
+            }
+            other => panic!("Expected a Runtime error, got {other:?}"),
+        }
+        assert_eq!(
+            err.render(source),
+            "[line 2] Runtime error[E0202]: Division by zero\n      2 / 0\n        ^"
+        );
+    }
+
+    #[test]
+    fn runtime_errors_report_operator_line() {
+        match evaluate("\"a\"\n+\n1") {
+            Err(CrustCoreErr::Runtime { line, .. }) => assert_eq!(line, 2),
+            other => panic!("Expected a Runtime error, got {other:?}"),
+        }
+        let statements = parse("let a = 1;\nprint a;\nprint b;").unwrap();
+        match Interpreter::with_output(Box::new(io::sink())).interpret(&statements) {
+            Err(err @ CrustCoreErr::Runtime { line: 3, .. }) => {
+                assert_eq!(
+                    err.to_string(),
+                    "[line 3] Runtime error: Undefined variable 'b'"
+                )
+            }
+            other => panic!("Expected a Runtime error on line 3, got {other:?}"),
+        }
+    }
+}
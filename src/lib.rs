@@ -1,16 +1,317 @@
-use util::CrustCoreResult;
+//! A tree-walking interpreter for the Crust language.
+//!
+//! The pipeline is also available piece by piece, for tools that want the tokens or the
+//! syntax tree rather than running a program:
+//!
+//! ```
+//! use crust::{parser::Parser, scanner::Scanner, token::Token};
+//!
+//! let tokens = Scanner::new("let x = 1;").scan_tokens().unwrap();
+//! assert_eq!(tokens[0].token, Token::Let);
+//! assert_eq!(tokens[1].token, Token::Identifier("x".into()));
+//!
+//! let program = Parser::new(tokens).parse_program().unwrap();
+//! assert_eq!(program.len(), 1);
+//! ```
 
-use crate::scanner::Scanner;
+use std::{fs, path::PathBuf};
 
-mod ast;
-mod scanner;
+use crust_grammar::token::SourceToken;
+use util::{CrustCoreErr, CrustCoreResult, SharedBuffer, SharedWarnings};
+
+use crate::{
+    ast::{AstPrinter, Expression, Statement},
+    parser::Parser,
+    scanner::Scanner,
+};
+
+pub mod ast;
+mod cursor;
+mod environment;
+mod fold;
+mod interpreter;
+mod lint;
+mod natives;
+pub mod parser;
+pub mod scanner;
+mod types;
 pub mod util;
+pub mod walk;
+
+pub use crust_grammar::token;
+pub use fold::strip_groupings;
+pub use interpreter::{Interpreter, ReadLine, Value};
+
+/// Where a program comes from, which decides whether bare expressions echo their value.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Mode {
+    /// A script file or `-e` source, where expression statements are silent.
+    #[default]
+    File,
+    /// Interactive input, where a final expression, with or without its `;`, is printed.
+    Repl,
+}
+
+/// Inspection switches for [`run_with`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RunOptions {
+    /// Print the scanned tokens.
+    pub dump_tokens: bool,
+    /// Print the parsed program.
+    pub dump_ast: bool,
+    /// Stop after parsing instead of executing the program.
+    pub check_only: bool,
+    pub mode: Mode,
+    /// Collects the warnings about the script, e.g. for unreachable code. Nothing is printed,
+    /// so the caller decides whether and how to report them.
+    pub warnings: SharedWarnings,
+}
 
 pub fn run(script: &str) -> CrustCoreResult<()> {
-    println!("Src: {script}");
+    run_with(script, &RunOptions::default())
+}
+
+pub fn run_with(script: &str, options: &RunOptions) -> CrustCoreResult<()> {
+    run_in(&Interpreter::new(), script, options)
+}
+
+/// An interpreter kept across several runs, so that the definitions of one, e.g. a line typed
+/// at the REPL, are visible to the next.
+#[derive(Default)]
+pub struct Session {
+    interpreter: Interpreter,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs `script` like [`run_with`], in the scope left behind by the previous runs.
+    pub fn run(&self, script: &str, options: &RunOptions) -> CrustCoreResult<()> {
+        run_in(&self.interpreter, script, options)
+    }
+}
+
+fn run_in(interpreter: &Interpreter, script: &str, options: &RunOptions) -> CrustCoreResult<()> {
+    options.warnings.extend(lint::check_indentation(script));
     let scanner = Scanner::new(script);
 
-    let tokens = scanner.scan_tokens();
-    println!("Tokens: {tokens:#?}");
+    let tokens = scanner.scan_tokens()?;
+    if options.dump_tokens {
+        println!("{tokens:#?}");
+    }
+
+    let program = match options.mode {
+        Mode::File => Parser::new(tokens).parse_program()?,
+        Mode::Repl => echo_value(Parser::new(tokens).parse_script()?),
+    };
+    check(&program, &options.warnings)?;
+    if options.dump_ast {
+        for statement in &program {
+            println!("{}", statement.accept(&AstPrinter {})?);
+        }
+    }
+
+    if options.check_only {
+        return Ok(());
+    }
+    interpreter.interpret(&program)
+}
+
+/// Type checks `program`, adding the warnings about it to `warnings`.
+fn check(program: &[Statement], warnings: &SharedWarnings) -> CrustCoreResult<()> {
+    types::check(program)?;
+    warnings.extend(lint::check_unreachable(program));
     Ok(())
 }
+
+/// Turns the final expression of REPL input into a `print` of it.
+fn echo_value((mut program, value): (Vec<Statement>, Option<Expression>)) -> Vec<Statement> {
+    match (value, program.pop()) {
+        (Some(expr), last) => {
+            program.extend(last);
+            program.push(Statement::Print { expr });
+        }
+        (None, Some(Statement::Expression { expr })) => program.push(Statement::Print { expr }),
+        (None, last) => program.extend(last),
+    }
+    program
+}
+
+/// Runs `script` and returns its value: that of a final expression, which may leave out its
+/// `;`, or `nil` when the script ends in any other statement. The script is type checked as
+/// with [`run_with`], but not linted, since there is nowhere to hand warnings to.
+pub fn eval(script: &str) -> CrustCoreResult<Value> {
+    let tokens = Scanner::new(script).scan_tokens()?;
+    let (program, value) = Parser::new(tokens).parse_script()?;
+    types::check(&program)?;
+    Interpreter::new().interpret_with_value(&program, value.as_ref())
+}
+
+/// Scans `source` without stopping at the first problem, returning the tokens that could be
+/// scanned together with the errors for the rest, e.g. for editors showing partial results.
+pub fn scan(source: &str) -> (Vec<SourceToken>, Vec<CrustCoreErr>) {
+    Scanner::new(source).scan()
+}
+
+/// Scans several sources at once, e.g. every file of a project, returning the tokens of each
+/// together with the errors of all of them, each attributed to the file it is in.
+pub fn scan_sources(
+    sources: &[(PathBuf, String)],
+) -> (Vec<(PathBuf, Vec<SourceToken>)>, Vec<CrustCoreErr>) {
+    let mut scanned = vec![];
+    let mut errors = vec![];
+    for (path, source) in sources {
+        let (tokens, file_errors) = scan(source);
+        errors.extend(file_errors.into_iter().map(|error| error.in_file(path)));
+        scanned.push((path.clone(), tokens));
+    }
+    (scanned, errors)
+}
+
+/// Reads and scans every file in `paths`, reporting all the files that could not be read or
+/// scanned in one `Multi`.
+pub fn scan_files(paths: &[PathBuf]) -> CrustCoreResult<Vec<(PathBuf, Vec<SourceToken>)>> {
+    let mut sources = vec![];
+    let mut errors = vec![];
+    for path in paths {
+        match fs::read_to_string(path) {
+            Ok(source) => sources.push((path.clone(), source)),
+            Err(err) => errors.push(
+                CrustCoreErr::Io {
+                    message: err.to_string(),
+                }
+                .in_file(path),
+            ),
+        }
+    }
+    let (scanned, scan_errors) = scan_sources(&sources);
+    errors.extend(scan_errors);
+    if errors.is_empty() {
+        Ok(scanned)
+    } else {
+        Err(CrustCoreErr::Multi { errors })
+    }
+}
+
+/// Whether `input` is unfinished, e.g. it has unclosed braces or ends inside a string, so a
+/// prompt should read further lines before running it.
+pub fn needs_more_input(input: &str) -> bool {
+    Scanner::new(input).needs_more_input()
+}
+
+/// Runs `script` like [`run`], but collects everything it prints and returns it instead of
+/// writing to stdout. The warnings are dropped.
+pub fn run_capture(script: &str) -> CrustCoreResult<String> {
+    let output = SharedBuffer::default();
+    let interpreter = Interpreter::with_output(Box::new(output.clone()));
+    run_in(&interpreter, script, &RunOptions::default())?;
+    Ok(output.contents())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_repl_echoes_expressions() {
+        let run = |source, mode| {
+            let output = SharedBuffer::default();
+            let interpreter = Interpreter::with_output(Box::new(output.clone()));
+            let options = RunOptions {
+                mode,
+                ..RunOptions::default()
+            };
+            run_in(&interpreter, source, &options).unwrap();
+            output.contents()
+        };
+        assert_eq!(run("1 + 2;", Mode::Repl), "3\n");
+        assert_eq!(run("1 + 2;", Mode::File), "");
+        assert_eq!(run("let x = 2;\nx * 4", Mode::Repl), "8\n");
+        assert_eq!(run("print 1;", Mode::Repl), "1\n");
+        assert_eq!(run("let x = 1;", Mode::Repl), "");
+    }
+
+    #[test]
+    fn scan_sources_attributes_errors_to_files() {
+        let sources = [
+            (PathBuf::from("clean.crust"), "print 1;".to_string()),
+            (
+                PathBuf::from("broken.crust"),
+                "let a = 1;\nprint @;".to_string(),
+            ),
+        ];
+        let (scanned, errors) = scan_sources(&sources);
+        assert_eq!(scanned[0].0, PathBuf::from("clean.crust"));
+        assert_eq!(scanned[0].1.len(), 4);
+        match &errors[..] {
+            [err @ CrustCoreErr::File { path, error }] => {
+                assert_eq!(path, &PathBuf::from("broken.crust"));
+                assert_eq!(error.line(), 2);
+                assert_eq!(
+                    err.to_string(),
+                    "broken.crust: [line 2] Error: Unexpected character '@'"
+                );
+            }
+            other => panic!("Expected one error in broken.crust, got {other:?}"),
+        }
+        let missing = scan_files(&[PathBuf::from("does/not/exist.crust")]).unwrap_err();
+        assert!(missing
+            .to_string()
+            .starts_with("does/not/exist.crust: I/O error:"));
+    }
+
+    #[test]
+    fn session_keeps_definitions_between_runs() {
+        let output = SharedBuffer::default();
+        let session = Session {
+            interpreter: Interpreter::with_output(Box::new(output.clone())),
+        };
+        let options = RunOptions {
+            mode: Mode::Repl,
+            ..RunOptions::default()
+        };
+        session.run("let x = 1;\n", &options).unwrap();
+        session
+            .run("fn double(n) { return n * 2; }\n", &options)
+            .unwrap();
+        session.run("double(x + 1)\n", &options).unwrap();
+        assert!(session.run("print y;\n", &options).is_err());
+        session.run("print x;\n", &options).unwrap();
+        assert_eq!(output.contents(), "4\n1\n");
+    }
+
+    #[test]
+    fn warnings_are_collected_for_the_caller() {
+        let options = RunOptions {
+            check_only: true,
+            ..RunOptions::default()
+        };
+        let source = "fn f() {\n\treturn 1;\n    print 2;\n}";
+        run_with(source, &options).unwrap();
+        let lines = options
+            .warnings
+            .take()
+            .iter()
+            .map(|warning| warning.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                "[line 3] Warning: Mixed tab and space indentation",
+                "[line 3] Warning: Unreachable code"
+            ]
+        );
+        assert!(options.warnings.take().is_empty());
+        // The warnings of a failing run are kept as well.
+        assert!(run_with("\tlet a = 1;\n  print ;", &options).is_err());
+        assert_eq!(options.warnings.take().len(), 1);
+    }
+
+    #[test]
+    fn run_capture_collects_prints() {
+        let output = run_capture("print \"Hello\";\nprint 42;").unwrap();
+        assert_eq!(output, "Hello\n42\n");
+    }
+}
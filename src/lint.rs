@@ -0,0 +1,139 @@
+use crate::{ast::Statement, util::CrustCoreWarning};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Indentation {
+    Tabs,
+    Spaces,
+    Mixed,
+}
+
+fn indentation(line: &str) -> Option<Indentation> {
+    // Whitespace-only lines say nothing about the indentation style.
+    if line.trim().is_empty() {
+        return None;
+    }
+    let leading = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+    if leading.is_empty() {
+        return None;
+    }
+    match (leading.contains('\t'), leading.contains(' ')) {
+        (true, false) => Some(Indentation::Tabs),
+        (false, true) => Some(Indentation::Spaces),
+        _ => Some(Indentation::Mixed),
+    }
+}
+
+/// Warns about the first line whose indentation disagrees with the indentation style the file
+/// started with, or which mixes tabs and spaces within its own indentation.
+pub fn check_indentation(source: &str) -> Option<CrustCoreWarning> {
+    let mut style: Option<Indentation> = None;
+    for (index, line) in source.lines().enumerate() {
+        let Some(indentation) = indentation(line) else {
+            continue;
+        };
+        let consistent = match style {
+            _ if indentation == Indentation::Mixed => false,
+            Some(style) => style == indentation,
+            None => true,
+        };
+        if !consistent {
+            return Some(CrustCoreWarning {
+                line: index + 1,
+                message: "Mixed tab and space indentation".to_string(),
+            });
+        }
+        style = Some(indentation);
+    }
+    None
+}
+
+/// Warns about the first statement after a `return` in each block, including a `return` nested
+/// in an inner `{ }` block. Later statements in the same block are not reported again.
+pub fn check_unreachable(program: &[Statement]) -> Vec<CrustCoreWarning> {
+    let mut warnings = vec![];
+    check_block(program, &mut warnings);
+    warnings
+}
+
+fn check_block(statements: &[Statement], warnings: &mut Vec<CrustCoreWarning>) {
+    let mut returned = false;
+    for statement in statements {
+        if returned {
+            warnings.push(CrustCoreWarning {
+                line: statement.line(),
+                message: "Unreachable code".to_string(),
+            });
+            break;
+        }
+        match statement {
+            Statement::Block { statements, .. } => check_block(statements, warnings),
+            Statement::For { body, .. } | Statement::Function { body, .. } => {
+                check_block(body, warnings)
+            }
+            _ => {}
+        }
+        returned = always_returns(statement);
+    }
+}
+
+fn always_returns(statement: &Statement) -> bool {
+    match statement {
+        Statement::Return { .. } => true,
+        Statement::Block { statements, .. } => statements.iter().any(always_returns),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, scanner::Scanner};
+
+    fn unreachable(source: &str) -> Vec<CrustCoreWarning> {
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+        check_unreachable(&Parser::new(tokens).parse_program().unwrap())
+    }
+
+    #[test]
+    fn mixed_indentation_across_lines() {
+        let source = "fn f() {\n\tlet a = 1;\n    let b = 2;\n}";
+        assert_eq!(
+            check_indentation(source),
+            Some(CrustCoreWarning {
+                line: 3,
+                message: "Mixed tab and space indentation".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn mixed_indentation_within_line() {
+        assert_eq!(check_indentation("a;\n \tb;").map(|w| w.line), Some(2));
+    }
+
+    #[test]
+    fn consistent_indentation() {
+        assert_eq!(check_indentation("{\n\ta;\n\t\tb;\n}"), None);
+        assert_eq!(check_indentation("{\n  a;\n\t\n    b;\n}"), None);
+    }
+
+    #[test]
+    fn code_after_return_is_unreachable() {
+        let source = "fn f() {\n  return 1;\n  print 2;\n  print 3;\n}";
+        assert_eq!(
+            unreachable(source),
+            vec![CrustCoreWarning {
+                line: 3,
+                message: "Unreachable code".to_string()
+            }]
+        );
+        assert_eq!(
+            unreachable("fn f() { { return; } let a = 1; }")
+                .iter()
+                .map(|w| w.line)
+                .collect::<Vec<_>>(),
+            vec![1]
+        );
+        assert!(unreachable("fn f() { print 1; return 1; }").is_empty());
+    }
+}
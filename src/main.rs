@@ -1,12 +1,19 @@
-use std::{env::args, fs};
+use std::{env::args, fs, path::Path, process};
+
+use crust::util::CrustCoreErr;
 
 mod err {
-    use std::io;
+    use std::{fmt, io};
+
+    use crust::util::CrustCoreErr;
 
     #[derive(Debug)]
     pub enum CrustErr {
         IoError(io::Error),
         CoreError(crust::util::CrustCoreErr),
+        Usage,
+        /// An error that has already been written to stderr, leaving only its exit code.
+        Reported(i32),
     }
     pub type CrustResult = Result<(), CrustErr>;
 
@@ -20,38 +27,516 @@ mod err {
             CrustErr::CoreError(err)
         }
     }
+
+    impl fmt::Display for CrustErr {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                CrustErr::IoError(err) => write!(f, "I/O error: {err}"),
+                CrustErr::CoreError(err) => write!(f, "{err}"),
+                CrustErr::Usage => write!(
+                    f,
+                    "Usage: crust [--dump-tokens] [--dump-ast] [--check-only] [script... | -e <source>]"
+                ),
+                CrustErr::Reported(_) => Ok(()),
+            }
+        }
+    }
+
+    /// Process exit code for an error, following the sysexits conventions: `EX_USAGE` for bad
+    /// arguments, `EX_DATAERR` for malformed scripts, `EX_SOFTWARE` for runtime failures and
+    /// `EX_IOERR` for I/O problems.
+    pub fn exit_code(err: &CrustErr) -> i32 {
+        match err {
+            CrustErr::Usage => 64,
+            CrustErr::IoError(_) => 74,
+            CrustErr::CoreError(err) => core_exit_code(err),
+            CrustErr::Reported(code) => *code,
+        }
+    }
+
+    /// Writes `err` to stderr with the lines of `source` it points at.
+    pub fn report(err: CrustCoreErr, source: &str) -> CrustErr {
+        eprintln!("{}", err.render(source));
+        CrustErr::Reported(core_exit_code(&err))
+    }
+
+    fn core_exit_code(err: &CrustCoreErr) -> i32 {
+        match err {
+            CrustCoreErr::Multi { errors } => errors.first().map_or(65, core_exit_code),
+            CrustCoreErr::Scan { .. } | CrustCoreErr::Parse { .. } => 65,
+            CrustCoreErr::Runtime { .. } => 70,
+            CrustCoreErr::File { error, .. } => core_exit_code(error),
+            CrustCoreErr::Io { .. } => 74,
+        }
+    }
+}
+
+mod repl {
+    use std::{env, io, path::PathBuf};
+
+    use rustyline::{error::ReadlineError, DefaultEditor};
+
+    use crate::err::{CrustErr, CrustResult};
+
+    /// Source of REPL input lines, so the prompt loop can run without a real terminal.
+    pub trait LineEditor {
+        /// Reads one line without its trailing newline, or `None` once input is exhausted.
+        fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>>;
+        fn add_history_entry(&mut self, entry: &str);
+    }
+
+    /// A terminal line editor with emacs-style keybindings and history kept in
+    /// `~/.crust_history` between sessions.
+    pub struct TerminalEditor {
+        editor: DefaultEditor,
+        history_path: Option<PathBuf>,
+    }
+
+    impl TerminalEditor {
+        pub fn new() -> io::Result<Self> {
+            let mut editor = DefaultEditor::new().map_err(to_io_error)?;
+            let history_path =
+                env::var_os("HOME").map(|home| PathBuf::from(home).join(".crust_history"));
+            if let Some(path) = &history_path {
+                // There is no history to load on the very first session.
+                let _ = editor.load_history(path);
+            }
+            Ok(Self {
+                editor,
+                history_path,
+            })
+        }
+
+        pub fn save_history(&mut self) -> io::Result<()> {
+            match &self.history_path {
+                Some(path) => self.editor.save_history(path).map_err(to_io_error),
+                None => Ok(()),
+            }
+        }
+    }
+
+    impl LineEditor for TerminalEditor {
+        fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>> {
+            match self.editor.readline(prompt) {
+                Ok(line) => Ok(Some(line)),
+                // Ctrl-C abandons the current input, like a blank continuation line.
+                Err(ReadlineError::Interrupted) => Ok(Some(String::new())),
+                Err(ReadlineError::Eof) => Ok(None),
+                Err(err) => Err(to_io_error(err)),
+            }
+        }
+
+        fn add_history_entry(&mut self, entry: &str) {
+            let _ = self.editor.add_history_entry(entry);
+        }
+    }
+
+    fn to_io_error(err: ReadlineError) -> io::Error {
+        match err {
+            ReadlineError::Io(err) => err,
+            err => io::Error::other(err),
+        }
+    }
+
+    /// Reads and executes input until `exit` or the end of input. Input that is unfinished,
+    /// like an unclosed block, is accumulated across lines until it is complete. An error in
+    /// one input is reported and the prompt carries on, so earlier definitions stay usable.
+    pub fn run_prompt(
+        editor: &mut dyn LineEditor,
+        execute: &mut dyn FnMut(&str) -> CrustResult,
+    ) -> CrustResult {
+        let mut input = String::new();
+        loop {
+            let prompt = if input.is_empty() { "> " } else { "... " };
+            let Some(line) = editor.read_line(prompt)? else {
+                break Ok(());
+            };
+
+            if input.is_empty() && line.trim() == "exit" {
+                break Ok(());
+            } else if !input.is_empty() && line.trim().is_empty() {
+                // A blank continuation line abandons the unfinished input.
+                input.clear();
+            } else {
+                input.push_str(&line);
+                input.push('\n');
+                if !crust::needs_more_input(&input) {
+                    editor.add_history_entry(input.trim_end());
+                    if let Err(err) = execute(&input) {
+                        if !matches!(err, CrustErr::Reported(_)) {
+                            eprintln!("{err}");
+                        }
+                    }
+                    input.clear();
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+enum Command {
+    #[default]
+    Prompt,
+    Files(Vec<String>),
+    Eval(String),
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    command: Command,
+    run: crust::RunOptions,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, err::CrustErr> {
+    let mut options = Options::default();
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dump-tokens" => options.run.dump_tokens = true,
+            "--dump-ast" => options.run.dump_ast = true,
+            "--check-only" => options.run.check_only = true,
+            "-e" if options.command == Command::Prompt => {
+                let source = args.next().ok_or(err::CrustErr::Usage)?;
+                options.command = Command::Eval(source.clone());
+            }
+            path if !path.starts_with('-') => match &mut options.command {
+                Command::Prompt => options.command = Command::Files(vec![path.to_string()]),
+                Command::Files(paths) => paths.push(path.to_string()),
+                Command::Eval(_) => return Err(err::CrustErr::Usage),
+            },
+            _ => return Err(err::CrustErr::Usage),
+        }
+    }
+    Ok(options)
 }
 
 fn main() {
-    println!("Hello from Crust!");
     let args = args().collect::<Vec<String>>();
 
-    match &args[..] {
-        [_] => run_prompt(),
-        [_, path] => run_file(path),
-        _ => panic!(),
+    if let Err(err) = parse_args(&args).and_then(|options| run(&options)) {
+        if !matches!(err, err::CrustErr::Reported(_)) {
+            eprintln!("{err}");
+        }
+        process::exit(err::exit_code(&err));
     }
-    .unwrap();
 }
 
-fn run_file(path: &str) -> err::CrustResult {
-    let script = fs::read_to_string(path)?;
-    crust::run(&script).map_err(|err| err.into())
+fn run(options: &Options) -> err::CrustResult {
+    match &options.command {
+        Command::Prompt => {
+            println!("Hello from Crust!");
+            run_prompt(&options.run)
+        }
+        Command::Files(paths) => run_files(paths, &options.run),
+        Command::Eval(source) => {
+            let result = crust::run_with(source, &options.run);
+            report_warnings(&options.run);
+            result.map_err(|err| err::report(err, source))
+        }
+    }
 }
 
-fn run_prompt() -> err::CrustResult {
-    let stdin = std::io::stdin();
-    loop {
-        let mut line = String::new();
-        match stdin.read_line(&mut line) {
-            Ok(_) => {
-                if line.starts_with("exit") {
-                    break Ok(());
-                } else {
-                    crust::run(&line)?;
-                }
+/// Runs each script in turn, even after one fails. With several scripts, each error names its
+/// file, and the exit code is that of the first script to fail.
+fn run_files(paths: &[String], options: &crust::RunOptions) -> err::CrustResult {
+    let name_files = paths.len() > 1;
+    let mut result = Ok(());
+    for path in paths {
+        let ran = run_file(path, options, name_files);
+        result = result.and(ran);
+    }
+    result
+}
+
+/// Runs the script at `path`, reporting its errors against its source.
+fn run_file(path: &str, options: &crust::RunOptions, name_file: bool) -> err::CrustResult {
+    let (script, result) = match fs::read_to_string(path) {
+        Ok(script) => {
+            let result = crust::run_with(&script, options);
+            report_warnings(options);
+            (script, result)
+        }
+        Err(err) => {
+            let err = CrustCoreErr::Io {
+                message: err.to_string(),
+            };
+            (String::new(), Err(err))
+        }
+    };
+    result.map_err(|err| {
+        let err = if name_file {
+            err.in_file(Path::new(path))
+        } else {
+            err
+        };
+        err::report(err, &script)
+    })
+}
+
+/// Writes the warnings the last run collected to stderr, ahead of any error it failed with.
+fn report_warnings(options: &crust::RunOptions) {
+    for warning in options.warnings.take() {
+        eprintln!("{warning}");
+    }
+}
+
+fn run_prompt(options: &crust::RunOptions) -> err::CrustResult {
+    let options = &crust::RunOptions {
+        mode: crust::Mode::Repl,
+        ..options.clone()
+    };
+    let mut editor = repl::TerminalEditor::new()?;
+    let session = crust::Session::new();
+    let result = repl::run_prompt(&mut editor, &mut |input| {
+        let result = session.run(input, options);
+        report_warnings(options);
+        result.map_err(|err| err::report(err, input))
+    });
+    if let Err(err) = editor.save_history() {
+        eprintln!("Could not save REPL history: {err}");
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::VecDeque, io};
+
+    use crust::util::{CrustCoreErr, ErrorKind};
+
+    use super::{
+        err::{exit_code, report, CrustErr},
+        parse_args,
+        repl::{run_prompt, LineEditor},
+        Command, Options,
+    };
+
+    struct MockEditor {
+        lines: VecDeque<&'static str>,
+        prompts: Vec<String>,
+        history: Vec<String>,
+    }
+
+    impl MockEditor {
+        fn new(lines: &[&'static str]) -> Self {
+            Self {
+                lines: lines.iter().copied().collect(),
+                prompts: vec![],
+                history: vec![],
             }
-            Err(_) => todo!(),
         }
     }
+
+    impl LineEditor for MockEditor {
+        fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>> {
+            self.prompts.push(prompt.to_string());
+            Ok(self.lines.pop_front().map(|line| line.to_string()))
+        }
+
+        fn add_history_entry(&mut self, entry: &str) {
+            self.history.push(entry.to_string());
+        }
+    }
+
+    fn run_mock(editor: &mut MockEditor) -> Vec<String> {
+        let mut executed = vec![];
+        run_prompt(editor, &mut |input| {
+            executed.push(input.to_string());
+            Ok(())
+        })
+        .unwrap();
+        executed
+    }
+
+    #[test]
+    fn prompt_stops_at_exit() {
+        let mut editor = MockEditor::new(&["print 1;", "exit", "print 2;"]);
+        assert_eq!(run_mock(&mut editor), vec!["print 1;\n"]);
+        assert_eq!(editor.history, vec!["print 1;"]);
+    }
+
+    #[test]
+    fn prompt_only_stops_at_a_bare_exit() {
+        let mut editor = MockEditor::new(&["let exitcode = 3;", "  exit  ", "print 2;"]);
+        assert_eq!(run_mock(&mut editor), vec!["let exitcode = 3;\n"]);
+    }
+
+    #[test]
+    fn prompt_carries_on_after_an_error() {
+        let mut editor = MockEditor::new(&["let x = 1;", "print y;", "print x;"]);
+        let mut executed = vec![];
+        run_prompt(&mut editor, &mut |input| {
+            executed.push(input.to_string());
+            match input {
+                "print y;\n" => Err(report(
+                    CrustCoreErr::Runtime {
+                        kind: ErrorKind::UndefinedVariable,
+                        line: 1,
+                        message: "Undefined variable 'y'".to_string(),
+                        span: None,
+                    },
+                    input,
+                )),
+                _ => Ok(()),
+            }
+        })
+        .unwrap();
+        assert_eq!(executed, vec!["let x = 1;\n", "print y;\n", "print x;\n"]);
+    }
+
+    #[test]
+    fn prompt_stops_at_end_of_input() {
+        let mut editor = MockEditor::new(&["print 1;"]);
+        assert_eq!(run_mock(&mut editor), vec!["print 1;\n"]);
+        assert_eq!(editor.prompts, vec!["> ", "> "]);
+    }
+
+    #[test]
+    fn prompt_joins_continuation_lines() {
+        let mut editor = MockEditor::new(&["fn f() {", "print 1;", "}", "{", "", "exit"]);
+        assert_eq!(run_mock(&mut editor), vec!["fn f() {\nprint 1;\n}\n"]);
+        assert_eq!(
+            editor.prompts,
+            vec!["> ", "... ", "... ", "> ", "... ", "> "]
+        );
+        assert_eq!(editor.history, vec!["fn f() {\nprint 1;\n}"]);
+    }
+
+    /// Ends input once its lines run out, then fails every further read.
+    struct BrokenEditor {
+        lines: VecDeque<Option<&'static str>>,
+    }
+
+    impl LineEditor for BrokenEditor {
+        fn read_line(&mut self, _prompt: &str) -> io::Result<Option<String>> {
+            match self.lines.pop_front() {
+                Some(line) => Ok(line.map(|line| line.to_string())),
+                None => Err(io::Error::new(io::ErrorKind::BrokenPipe, "stdin closed")),
+            }
+        }
+
+        fn add_history_entry(&mut self, _entry: &str) {}
+    }
+
+    #[test]
+    fn prompt_exits_cleanly_at_eof_and_reports_read_errors() {
+        let mut execute = |_: &str| Ok(());
+        let mut editor = BrokenEditor {
+            lines: VecDeque::from([Some("print 1;"), None]),
+        };
+        assert!(run_prompt(&mut editor, &mut execute).is_ok());
+        // The loop stopped at the end of input instead of reading on into the error.
+        assert!(editor.lines.is_empty());
+
+        let mut editor = BrokenEditor {
+            lines: VecDeque::from([Some("print 1;")]),
+        };
+        let err = run_prompt(&mut editor, &mut execute).unwrap_err();
+        assert!(matches!(&err, CrustErr::IoError(err) if err.kind() == io::ErrorKind::BrokenPipe));
+        assert_eq!(exit_code(&err), 74);
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn dispatch_arguments() {
+        let command = |arguments: &[&str]| parse_args(&args(arguments)).ok().map(|o| o.command);
+        assert_eq!(command(&["crust"]), Some(Command::Prompt));
+        assert_eq!(
+            command(&["crust", "script.cr"]),
+            Some(Command::Files(vec!["script.cr".to_string()]))
+        );
+        assert_eq!(
+            command(&["crust", "a.cr", "--check-only", "b.cr"]),
+            Some(Command::Files(vec!["a.cr".to_string(), "b.cr".to_string()]))
+        );
+        assert_eq!(
+            command(&["crust", "-e", "print 1 + 2;"]),
+            Some(Command::Eval("print 1 + 2;".to_string()))
+        );
+    }
+
+    #[test]
+    fn invalid_arguments_are_a_usage_error() {
+        for arguments in [
+            &["crust", "-e"][..],
+            &["crust", "-e", "1;", "a.cr"],
+            &["crust", "--dump-everything"],
+        ] {
+            let err = parse_args(&args(arguments)).unwrap_err();
+            assert!(matches!(err, CrustErr::Usage));
+            assert_eq!(exit_code(&err), 64);
+            assert!(err.to_string().starts_with("Usage: crust"));
+        }
+    }
+
+    #[test]
+    fn parse_inspection_flags() {
+        assert_eq!(
+            parse_args(&args(&["crust", "--dump-tokens", "a.cr", "--check-only"])).ok(),
+            Some(Options {
+                command: Command::Files(vec!["a.cr".to_string()]),
+                run: crust::RunOptions {
+                    dump_tokens: true,
+                    dump_ast: false,
+                    check_only: true,
+                    mode: crust::Mode::File,
+                    warnings: Default::default(),
+                },
+            })
+        );
+        assert_eq!(
+            parse_args(&args(&["crust", "--dump-ast"]))
+                .ok()
+                .map(|o| o.run.dump_ast),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn exit_codes() {
+        let scan = CrustCoreErr::Scan {
+            kind: ErrorKind::UnexpectedCharacter,
+            line: 1,
+            offset: 0,
+            message: "Unexpected character".to_string(),
+        };
+        let runtime = CrustCoreErr::Runtime {
+            kind: ErrorKind::InvalidOperation,
+            line: 1,
+            message: "Cannot add Integer and Str".to_string(),
+            span: None,
+        };
+        assert_eq!(exit_code(&CrustErr::CoreError(scan)), 65);
+        assert_eq!(
+            exit_code(&CrustErr::CoreError(CrustCoreErr::Parse {
+                kind: ErrorKind::Expected,
+                line: 1,
+                message: "Expected expression".to_string(),
+                span: None,
+            })),
+            65
+        );
+        assert_eq!(exit_code(&CrustErr::CoreError(runtime)), 70);
+        assert_eq!(
+            exit_code(&CrustErr::CoreError(CrustCoreErr::Multi { errors: vec![] })),
+            65
+        );
+        assert_eq!(
+            exit_code(&CrustErr::IoError(io::Error::other("broken pipe"))),
+            74
+        );
+        let parse = CrustCoreErr::Parse {
+            kind: ErrorKind::Expected,
+            line: 1,
+            message: "Expected expression".to_string(),
+            span: Some(0..1),
+        };
+        let reported = report(parse, "+");
+        assert_eq!(reported.to_string(), "");
+        assert_eq!(exit_code(&reported), 65);
+    }
 }
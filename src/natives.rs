@@ -0,0 +1,117 @@
+use std::rc::Rc;
+
+use crust_grammar::symbol::Symbol;
+
+use crate::{
+    environment::Environment,
+    interpreter::{runtime_error, Interpreter, NativeFunction, Value},
+    util::{CrustCoreResult, ErrorKind},
+};
+
+/// Defines the built-in functions in the global scope.
+pub fn define_natives(globals: &mut Environment) {
+    define(globals, "print", 1, |interpreter, args, line| {
+        interpreter.write_line(&args[0], line)?;
+        Ok(Value::Nil)
+    });
+    define(globals, "clock", 0, |interpreter, _, _| {
+        Ok(Value::Float(interpreter.now()))
+    });
+    define(globals, "read_line", 0, |interpreter, _, line| {
+        interpreter.read_line(line)
+    });
+    define(globals, "len", 1, |_, args, line| match &args[0] {
+        Value::Str(text) => Ok(Value::Integer(text.chars().count() as i64)),
+        other => Err(runtime_error(
+            ErrorKind::InvalidOperation,
+            line,
+            format!("len expects a Str, found {}", other.type_name()),
+        )),
+    });
+    define(globals, "apply", 2, |interpreter, args, line| {
+        interpreter.call(args[0].clone(), vec![args[1].clone()], line)
+    });
+    define(globals, "str", 1, |_, args, _| {
+        Ok(Value::Str(args[0].to_string()))
+    });
+    define(globals, "int", 1, |_, args, line| {
+        to_integer(&args[0], line)
+    });
+    define(globals, "float", 1, |_, args, line| {
+        to_float(&args[0], line)
+    });
+}
+
+/// `int(v)`: parses a Str, surrounding whitespace aside, and truncates a Float towards zero.
+fn to_integer(value: &Value, line: usize) -> CrustCoreResult<Value> {
+    match value {
+        Value::Integer(value) => Ok(Value::Integer(*value)),
+        // `i64::MAX as f64` is 2^63, one past the largest Integer.
+        Value::Float(value)
+            if value.is_finite() && *value >= i64::MIN as f64 && *value < i64::MAX as f64 =>
+        {
+            Ok(Value::Integer(value.trunc() as i64))
+        }
+        Value::Float(value) => Err(runtime_error(
+            ErrorKind::InvalidOperation,
+            line,
+            format!("int cannot convert {value} to an Integer"),
+        )),
+        Value::Str(text) => text.trim().parse().map(Value::Integer).map_err(|_| {
+            runtime_error(
+                ErrorKind::InvalidOperation,
+                line,
+                format!("int cannot parse '{text}' as an Integer"),
+            )
+        }),
+        other => Err(runtime_error(
+            ErrorKind::InvalidOperation,
+            line,
+            format!(
+                "int expects a Str, Integer or Float, found {}",
+                other.type_name()
+            ),
+        )),
+    }
+}
+
+/// `float(v)`: parses a Str, surrounding whitespace aside, and converts an Integer.
+fn to_float(value: &Value, line: usize) -> CrustCoreResult<Value> {
+    match value {
+        Value::Float(value) => Ok(Value::Float(*value)),
+        Value::Integer(value) => Ok(Value::Float(*value as f64)),
+        Value::Str(text) => text.trim().parse().map(Value::Float).map_err(|_| {
+            runtime_error(
+                ErrorKind::InvalidOperation,
+                line,
+                format!("float cannot parse '{text}' as a Float"),
+            )
+        }),
+        other => Err(runtime_error(
+            ErrorKind::InvalidOperation,
+            line,
+            format!(
+                "float expects a Str, Integer or Float, found {}",
+                other.type_name()
+            ),
+        )),
+    }
+}
+
+fn define(
+    globals: &mut Environment,
+    name: &'static str,
+    arity: usize,
+    function: impl Fn(&Interpreter, &[Value], usize) -> CrustCoreResult<Value> + 'static,
+) {
+    let native = NativeFunction {
+        name,
+        arity,
+        function: Box::new(function),
+    };
+    globals.define(
+        Symbol::intern(name),
+        Value::NativeFunction(Rc::new(native)),
+        false,
+    );
+}
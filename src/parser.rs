@@ -0,0 +1,1581 @@
+use crust_grammar::{
+    symbol::Symbol,
+    token::{SourceToken, StringPart, Token, TokenType},
+};
+
+use crate::{
+    ast::{Expression, Parameter, Statement},
+    cursor::TokenCursor,
+    environment::is_discard,
+    fold::{fold, fold_program},
+    scanner::Scanner,
+    util::{CrustCoreErr, CrustCoreResult, ErrorKind},
+};
+
+pub struct Parser {
+    tokens: TokenCursor,
+    function_depth: usize,
+    /// Whether a line break ends a statement, as well as a `;`.
+    newline_terminated: bool,
+    /// How deeply expressions and blocks are nested at the current token.
+    depth: usize,
+    max_depth: usize,
+    /// How many operators of the chains being parsed, like the `+`s of `a + b + c`, come
+    /// before the current token.
+    chained: usize,
+}
+
+/// The deepest nesting of expressions and blocks a [`Parser`] accepts by default. Every level
+/// of parentheses goes through the whole descent from an assignment down to a primary
+/// expression, so this stays inside the 2 MiB stack of a spawned thread even in debug builds.
+pub const DEFAULT_MAX_DEPTH: usize = 64;
+
+/// The binary and logical operators, from the loosest level to the tightest.
+const LEVELS: [&[TokenType]; 7] = [
+    &[TokenType::QuestionQuestion],
+    &[TokenType::Or],
+    &[TokenType::And],
+    &[TokenType::BangEqual, TokenType::EqualEqual],
+    &[
+        TokenType::Greater,
+        TokenType::GreaterEqual,
+        TokenType::Less,
+        TokenType::LessEqual,
+    ],
+    &[TokenType::Minus, TokenType::Plus],
+    &[TokenType::Slash, TokenType::Star, TokenType::Percent],
+];
+
+/// The loosest level of [`LEVELS`], which the conditional's condition starts from.
+const COALESCE: usize = 0;
+/// The tightest level of [`LEVELS`] whose operators are logical, i.e. build
+/// [`Expression::Logical`] and may skip their right operand.
+const AND: usize = 2;
+
+/// The most operators the chains around a token may have, e.g. `print 1 + 1 + ... + 1;` with
+/// this many `+`s. The parser and the interpreter loop over a chain, but its tree still nests
+/// every operator in the next one's left operand, and dropping that tree recurses.
+const MAX_CHAINED: usize = 4096;
+
+impl Parser {
+    pub fn new(mut tokens: Vec<SourceToken>) -> Self {
+        tokens.retain(|token| token.token != Token::Newline && !is_comment(token));
+        Self::from_tokens(tokens, false)
+    }
+
+    /// A parser for semicolon-free code, scanned with
+    /// [`Scanner::emit_newlines`](crate::scanner::Scanner::emit_newlines), where a line break
+    /// also ends a statement. A line continues onto the next one when it ends in an operator,
+    /// inside parentheses or brackets, or when the next line starts with an operator that
+    /// cannot begin a statement, such as `*` or `and`.
+    pub fn with_newlines(mut tokens: Vec<SourceToken>) -> Self {
+        tokens.retain(|token| !is_comment(token));
+        Self::from_tokens(terminating_newlines(tokens), true)
+    }
+
+    fn from_tokens(tokens: Vec<SourceToken>, newline_terminated: bool) -> Self {
+        Self {
+            tokens: TokenCursor::new(tokens),
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+            chained: 0,
+            function_depth: 0,
+            newline_terminated,
+        }
+    }
+
+    /// Limits how deeply expressions and blocks may nest, e.g. `((((1))))` is four levels.
+    /// Deeper input is a "Maximum nesting depth exceeded" error rather than a stack overflow.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn parse_program(self) -> CrustCoreResult<Vec<Statement>> {
+        let (statements, errors) = self.parse_program_lenient();
+        if !errors.is_empty() {
+            Err(CrustCoreErr::Multi { errors })
+        } else {
+            Ok(statements)
+        }
+    }
+
+    /// Parses the whole program without stopping at the first problem, returning the
+    /// statements that could be parsed together with the errors for the rest, e.g. for editors
+    /// working on unfinished code. Each bad statement is skipped up to the next statement
+    /// boundary.
+    pub fn parse_program_lenient(mut self) -> (Vec<Statement>, Vec<CrustCoreErr>) {
+        let mut statements: Vec<Statement> = vec![];
+        let mut errors: Vec<CrustCoreErr> = vec![];
+        while !self.tokens.is_at_end() {
+            match self.declaration() {
+                Ok(statement) => statements.push(statement),
+                Err(e) => {
+                    errors.push(e);
+                    // A chain cut short by the error leaves its levels behind.
+                    self.depth = 0;
+                    self.chained = 0;
+                    self.synchronize();
+                }
+            }
+        }
+        (fold_program(statements), errors)
+    }
+
+    /// Parses a program whose final expression may leave out its `;`, returning that expression
+    /// separately as the value of the program.
+    pub fn parse_script(mut self) -> CrustCoreResult<(Vec<Statement>, Option<Expression>)> {
+        let (statements, value) = self.statements_and_value(TokenType::Eof)?;
+        Ok((fold_program(statements), value.map(|value| fold(&value))))
+    }
+
+    fn declaration(&mut self) -> CrustCoreResult<Statement> {
+        // A line break is left after statements that end in a `}` rather than a terminator.
+        self.tokens.matches(&[TokenType::Newline]);
+        if self.starts_function_declaration() {
+            self.tokens.advance();
+            self.function_declaration()
+        } else if self.tokens.matches(&[TokenType::Let]) {
+            self.let_declaration()
+        } else if self.tokens.matches(&[TokenType::Const]) {
+            self.const_declaration()
+        } else {
+            self.statement()
+        }
+    }
+
+    fn function_declaration(&mut self) -> CrustCoreResult<Statement> {
+        let line = self.tokens.previous().line;
+        let name = self.identifier("Expected function name")?;
+        self.tokens
+            .consume(TokenType::LeftParen, "Expected '(' after function name")?;
+        let (params, duplicate) = self.parameters()?;
+        let return_type = if self.tokens.matches(&[TokenType::Arrow]) {
+            Some(
+                self.identifier("Expected return type after '->'")?
+                    .to_string(),
+            )
+        } else {
+            None
+        };
+        self.tokens
+            .consume(TokenType::LeftBrace, "Expected '{' before function body")?;
+
+        self.function_depth += 1;
+        let body = self.block();
+        self.function_depth -= 1;
+
+        if let Some(duplicate) = duplicate {
+            return Err(duplicate);
+        }
+        Ok(Statement::Function {
+            name,
+            params,
+            return_type,
+            body: body?,
+            line,
+        })
+    }
+
+    /// The parameters of a function after its `(`, up to and including the `)`. A name other
+    /// than the discard `_` may only appear once. A repeated name does not stop the parse, so
+    /// that the rest of the function is still read: it comes back as the error for the caller
+    /// to return once the whole function is parsed.
+    fn parameters(&mut self) -> CrustCoreResult<(Vec<Parameter>, Option<CrustCoreErr>)> {
+        let mut params: Vec<Parameter> = vec![];
+        let mut duplicate = None;
+        if !self.tokens.check(TokenType::RightParen) {
+            loop {
+                let name = self.identifier("Expected parameter name")?;
+                if duplicate.is_none()
+                    && !is_discard(name)
+                    && params.iter().any(|param| param.name == name)
+                {
+                    let token = self.tokens.previous();
+                    duplicate = Some(CrustCoreErr::Parse {
+                        kind: ErrorKind::DuplicateParameter,
+                        line: token.line,
+                        message: format!("Duplicate parameter '{name}'"),
+                        span: Some(token.offset..token.offset + token.length),
+                    });
+                }
+                let annotation = if self.tokens.matches(&[TokenType::Colon]) {
+                    Some(self.identifier("Expected type after ':'")?.to_string())
+                } else {
+                    None
+                };
+                params.push(Parameter { name, annotation });
+                // A trailing comma may follow the last parameter.
+                if !self.tokens.matches(&[TokenType::Comma])
+                    || self.tokens.check(TokenType::RightParen)
+                {
+                    break;
+                }
+            }
+        }
+        self.tokens
+            .consume(TokenType::RightParen, "Expected ')' after parameters")?;
+        Ok((params, duplicate))
+    }
+
+    /// Parses a lambda after its `fn`. A final expression in the body without a trailing `;`
+    /// becomes a `return` of it, like the value of a block expression.
+    fn lambda(&mut self) -> CrustCoreResult<Expression> {
+        let line = self.tokens.previous().line;
+        self.tokens
+            .consume(TokenType::LeftParen, "Expected '(' after 'fn'")?;
+        let (params, duplicate) = self.parameters()?;
+        self.tokens
+            .consume(TokenType::LeftBrace, "Expected '{' before lambda body")?;
+
+        self.function_depth += 1;
+        let body = self.nested(|parser| parser.statements_and_value(TokenType::RightBrace));
+        self.function_depth -= 1;
+
+        let (mut body, value) = body?;
+        self.tokens
+            .consume(TokenType::RightBrace, "Expected '}' after block")?;
+        if let Some(value) = value {
+            body.push(Statement::Return {
+                line: value.line(),
+                value: Some(value),
+            });
+        }
+        if let Some(duplicate) = duplicate {
+            return Err(duplicate);
+        }
+        Ok(Expression::Lambda { params, body, line })
+    }
+
+    /// Whether the next tokens are `fn name`, declaring a function rather than starting a
+    /// lambda.
+    fn starts_function_declaration(&self) -> bool {
+        self.tokens.check(TokenType::Fn) && self.tokens.check_next(TokenType::Identifier)
+    }
+
+    fn let_declaration(&mut self) -> CrustCoreResult<Statement> {
+        let line = self.tokens.previous().line;
+        let mutable = self.tokens.matches(&[TokenType::Mut]);
+        let name = self.identifier("Expected variable name")?;
+        let annotation = if self.tokens.matches(&[TokenType::Colon]) {
+            Some(self.identifier("Expected type after ':'")?.to_string())
+        } else {
+            None
+        };
+        let initializer = if self.tokens.matches(&[TokenType::Equal]) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+        self.terminator("Expected ';' after variable declaration")?;
+        Ok(Statement::Let {
+            name,
+            mutable,
+            annotation,
+            initializer,
+            line,
+        })
+    }
+
+    fn const_declaration(&mut self) -> CrustCoreResult<Statement> {
+        let line = self.tokens.previous().line;
+        let name = self.identifier("Expected constant name")?;
+        self.tokens
+            .consume(TokenType::Equal, "Expected '=' after constant name")?;
+        let initializer = self.expression()?;
+        self.terminator("Expected ';' after constant declaration")?;
+        Ok(Statement::Const {
+            name,
+            initializer,
+            line,
+        })
+    }
+
+    fn statement(&mut self) -> CrustCoreResult<Statement> {
+        if self.tokens.matches(&[TokenType::Print]) {
+            let expr = self.expression()?;
+            self.terminator("Expected ';' after value")?;
+            Ok(Statement::Print { expr })
+        } else if self.tokens.matches(&[TokenType::Return]) {
+            self.return_statement()
+        } else if self.tokens.matches(&[TokenType::For]) {
+            self.for_statement()
+        } else if self.tokens.matches(&[TokenType::LeftBrace]) {
+            let line = self.tokens.previous().line;
+            Ok(Statement::Block {
+                statements: self.block()?,
+                line,
+            })
+        } else {
+            let expr = self.expression()?;
+            self.terminator("Expected ';' after expression")?;
+            Ok(Statement::Expression { expr })
+        }
+    }
+
+    fn for_statement(&mut self) -> CrustCoreResult<Statement> {
+        let line = self.tokens.previous().line;
+        let name = self.identifier("Expected loop variable name after 'for'")?;
+        self.tokens
+            .consume(TokenType::In, "Expected 'in' after loop variable")?;
+        let start = self.expression()?;
+        let inclusive = match self.tokens.peek().token {
+            Token::DotDot => false,
+            Token::DotDotEqual => true,
+            _ => {
+                return Err(self.tokens.error(
+                    ErrorKind::Expected,
+                    "Expected '..' or '..=' in for loop range",
+                ))
+            }
+        };
+        self.tokens.advance();
+        let end = self.expression()?;
+        self.tokens
+            .consume(TokenType::LeftBrace, "Expected '{' before loop body")?;
+        Ok(Statement::For {
+            name,
+            start,
+            end,
+            inclusive,
+            body: self.block()?,
+            line,
+        })
+    }
+
+    fn return_statement(&mut self) -> CrustCoreResult<Statement> {
+        let line = self.tokens.previous().line;
+        if self.function_depth == 0 {
+            return Err(CrustCoreErr::Parse {
+                kind: ErrorKind::TopLevelReturn,
+                line,
+                message: "Cannot return from top-level code".to_string(),
+                span: None,
+            });
+        }
+        let value = if self.at_terminator() {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.terminator("Expected ';' after return value")?;
+        Ok(Statement::Return { value, line })
+    }
+
+    /// Parses the statements of a block whose opening `{` has already been consumed.
+    fn block(&mut self) -> CrustCoreResult<Vec<Statement>> {
+        self.nested(|parser| {
+            let mut statements = vec![];
+            while !parser.tokens.check(TokenType::RightBrace) && !parser.tokens.is_at_end() {
+                statements.push(parser.declaration()?);
+            }
+            parser
+                .tokens
+                .consume(TokenType::RightBrace, "Expected '}' after block")?;
+            Ok(statements)
+        })
+    }
+
+    /// Parses a block in expression position, after its `{`. A final expression without a
+    /// trailing `;` becomes the block's value.
+    fn block_expression(&mut self) -> CrustCoreResult<Expression> {
+        let line = self.tokens.previous().line;
+        let (statements, value) = self.statements_and_value(TokenType::RightBrace)?;
+        self.tokens
+            .consume(TokenType::RightBrace, "Expected '}' after block")?;
+        Ok(Expression::Block {
+            statements,
+            value: value.map(Box::new),
+            line,
+        })
+    }
+
+    /// Parses statements up to `end`, where an expression directly before `end` without a
+    /// trailing `;` is returned as their value.
+    fn statements_and_value(
+        &mut self,
+        end: TokenType,
+    ) -> CrustCoreResult<(Vec<Statement>, Option<Expression>)> {
+        let mut statements = vec![];
+        while !self.tokens.check(end) && !self.tokens.is_at_end() {
+            self.tokens.matches(&[TokenType::Newline]);
+            if self.starts_statement() {
+                statements.push(self.declaration()?);
+                continue;
+            }
+            let expr = self.expression()?;
+            if self.tokens.check(end) {
+                return Ok((statements, Some(expr)));
+            }
+            self.terminator("Expected ';' after expression")?;
+            statements.push(Statement::Expression { expr });
+        }
+        Ok((statements, None))
+    }
+
+    /// Whether the next token begins a statement rather than an expression.
+    fn starts_statement(&self) -> bool {
+        self.starts_function_declaration()
+            || matches!(
+                self.tokens.peek().token,
+                Token::Let
+                    | Token::Const
+                    | Token::Print
+                    | Token::Return
+                    | Token::For
+                    | Token::LeftBrace
+            )
+    }
+
+    fn expression(&mut self) -> CrustCoreResult<Expression> {
+        self.nested(Self::assignment)
+    }
+
+    /// Parses one level deeper, failing once the nesting passes `max_depth` rather than
+    /// recursing until the stack overflows.
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> CrustCoreResult<T>,
+    ) -> CrustCoreResult<T> {
+        self.deepen()?;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Goes one level deeper, unless that would pass `max_depth`.
+    fn deepen(&mut self) -> CrustCoreResult<()> {
+        if self.depth >= self.max_depth {
+            return Err(self
+                .tokens
+                .error(ErrorKind::NestingTooDeep, "Maximum nesting depth exceeded"));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn assignment(&mut self) -> CrustCoreResult<Expression> {
+        let expr = self.conditional()?;
+        if self.tokens.matches(&[TokenType::Equal]) {
+            let line = self.tokens.previous().line;
+            let value = self.nested(Self::assignment)?;
+            return match expr {
+                Expression::Variable { name, line } => Ok(Expression::Assign {
+                    name,
+                    line,
+                    value: Box::new(value),
+                }),
+                _ => Err(CrustCoreErr::Parse {
+                    kind: ErrorKind::InvalidTarget,
+                    line,
+                    message: "Invalid assignment target".to_string(),
+                    span: None,
+                }),
+            };
+        }
+        Ok(expr)
+    }
+
+    /// `cond ? then : else` binds looser than every binary operator and tighter than `=`, so
+    /// `x = a or b ? c : d` assigns the whole conditional, while `a ? b : c = d` is an invalid
+    /// assignment target. The else branch nests to the right: `a ? b : c ? d : e` is
+    /// `a ? b : (c ? d : e)`.
+    fn conditional(&mut self) -> CrustCoreResult<Expression> {
+        let condition = self.chain(COALESCE)?;
+        if self.tokens.matches(&[TokenType::Question]) {
+            let then_branch = self.expression()?;
+            self.tokens
+                .consume(TokenType::Colon, "Expected ':' in conditional expression")?;
+            let else_branch = self.nested(Self::conditional)?;
+            return Ok(Expression::Conditional {
+                condition: Box::new(condition),
+                then_branch: Box::new(then_branch),
+                else_branch: Box::new(else_branch),
+            });
+        }
+        Ok(condition)
+    }
+
+    /// Parses the operators of [`LEVELS`] from `level` on, e.g. all of `a or b == c + d * e`.
+    /// The operators of a level group to the left and are parsed in a loop, each with a right
+    /// operand that only reaches as far as the tighter levels, so that a run like `a + b + c`
+    /// does not nest the parser. Operators only count against [`MAX_CHAINED`], not `max_depth`.
+    fn chain(&mut self, level: usize) -> CrustCoreResult<Expression> {
+        let chained = self.chained;
+        let mut expr = self.unary()?;
+        while let Some(level) = self.operator_level(level) {
+            self.link()?;
+            let op = self.tokens.advance().clone();
+            let right = self
+                .chain(level + 1)
+                .map_err(|err| from_operator(err, &op))?;
+            expr = match level {
+                COALESCE..=AND => Expression::logical(expr, op, right),
+                _ => Expression::binary(expr, op, right),
+            };
+        }
+        self.chained = chained;
+        Ok(expr)
+    }
+
+    /// The level in [`LEVELS`] of the operator at the next token, if it is `level` or tighter.
+    fn operator_level(&self, level: usize) -> Option<usize> {
+        (level..LEVELS.len()).find(|&level| {
+            LEVELS[level]
+                .iter()
+                .any(|operator| self.tokens.check(*operator))
+        })
+    }
+
+    /// Counts one more operator of a chain, unless that would pass [`MAX_CHAINED`].
+    fn link(&mut self) -> CrustCoreResult<()> {
+        if self.chained >= MAX_CHAINED {
+            return Err(self.tokens.error(
+                ErrorKind::NestingTooDeep,
+                "Too many operators in one expression",
+            ));
+        }
+        self.chained += 1;
+        Ok(())
+    }
+
+    fn unary(&mut self) -> CrustCoreResult<Expression> {
+        if self
+            .tokens
+            .matches(&[TokenType::Bang, TokenType::Minus, TokenType::Plus])
+        {
+            let op = self.tokens.previous().clone();
+            let right = self.nested(Self::unary)?;
+            return Ok(Expression::unary(op, right));
+        }
+        self.power()
+    }
+
+    /// `base ** exponent`, which is right-associative and binds tighter than a unary operator
+    /// on its left, so `-2 ** 2` is `-(2 ** 2)`, but not on its right, so `2 ** -1` is allowed.
+    fn power(&mut self) -> CrustCoreResult<Expression> {
+        let base = self.postfix()?;
+        if !self.tokens.matches(&[TokenType::StarStar]) {
+            return Ok(base);
+        }
+        let op = self.tokens.previous().clone();
+        let exponent = self
+            .nested(Self::unary)
+            .map_err(|err| from_operator(err, &op))?;
+        Ok(Expression::binary(base, op, exponent))
+    }
+
+    /// `x++` and `x--`, desugared to `{ let old = x; x = old + 1; old }` so that they evaluate to
+    /// the value from before the update. The hidden name cannot clash with a real variable.
+    fn postfix(&mut self) -> CrustCoreResult<Expression> {
+        let expr = self.call()?;
+        if !self
+            .tokens
+            .matches(&[TokenType::PlusPlus, TokenType::MinusMinus])
+        {
+            return Ok(expr);
+        }
+        let op = self.tokens.previous().clone();
+        let Expression::Variable { name, line } = expr else {
+            return Err(CrustCoreErr::Parse {
+                kind: ErrorKind::InvalidTarget,
+                line: op.line,
+                message: format!("Invalid target for '{}'", op.lexeme),
+                span: Some(op.offset..op.offset + op.length),
+            });
+        };
+        let old = Symbol::intern(&format!("{name}{}", op.lexeme));
+        let (token, symbol) = match op.token {
+            Token::PlusPlus => (Token::Plus, "+"),
+            _ => (Token::Minus, "-"),
+        };
+        let update = Expression::binary(
+            Expression::Variable { name: old, line },
+            SourceToken::new(token, symbol, op.offset, op.line),
+            Expression::literal(SourceToken::new(Token::Integer(1), "1", op.offset, op.line)),
+        );
+        Ok(Expression::Block {
+            statements: vec![
+                Statement::Let {
+                    name: old,
+                    mutable: false,
+                    annotation: None,
+                    initializer: Some(Expression::Variable { name, line }),
+                    line,
+                },
+                Statement::Expression {
+                    expr: Expression::Assign {
+                        name,
+                        line,
+                        value: Box::new(update),
+                    },
+                },
+            ],
+            value: Some(Box::new(Expression::Variable { name: old, line })),
+            line,
+        })
+    }
+
+    /// Calls and indexing, e.g. `f(a)[0](b)`, where each one nests the tree a level deeper and
+    /// counts against `max_depth`.
+    fn call(&mut self) -> CrustCoreResult<Expression> {
+        let depth = self.depth;
+        let mut expr = self.primary()?;
+        while self
+            .tokens
+            .matches(&[TokenType::LeftParen, TokenType::LeftBracket])
+        {
+            let line = self.tokens.previous().line;
+            self.deepen()?;
+            if self.tokens.previous().token == Token::LeftBracket {
+                let index = self.expression()?;
+                self.tokens
+                    .consume(TokenType::RightBracket, "Expected ']' after index")?;
+                expr = Expression::Index {
+                    target: Box::new(expr),
+                    index: Box::new(index),
+                    line,
+                };
+                continue;
+            }
+            let mut args = vec![];
+            if !self.tokens.check(TokenType::RightParen) {
+                loop {
+                    args.push(self.expression()?);
+                    // A trailing comma may follow the last argument.
+                    if !self.tokens.matches(&[TokenType::Comma])
+                        || self.tokens.check(TokenType::RightParen)
+                    {
+                        break;
+                    }
+                }
+            }
+            self.tokens
+                .consume(TokenType::RightParen, "Expected ')' after arguments")?;
+            expr = Expression::Call {
+                callee: Box::new(expr),
+                line,
+                args,
+            };
+        }
+        self.depth = depth;
+        Ok(expr)
+    }
+
+    fn primary(&mut self) -> CrustCoreResult<Expression> {
+        match self.tokens.peek().token {
+            Token::True
+            | Token::False
+            | Token::Nil
+            | Token::Integer(_)
+            | Token::Float(_)
+            | Token::Char(_)
+            | Token::String(_) => {
+                let value = self.tokens.advance().clone();
+                Ok(Expression::Literal { value })
+            }
+            Token::Interpolation(_) => {
+                let string = self.tokens.advance().clone();
+                let Token::Interpolation(parts) = &string.token else {
+                    unreachable!("just matched an interpolation");
+                };
+                let parts = parts
+                    .iter()
+                    .map(|part| match part {
+                        StringPart::Text(text) => Ok(Expression::literal(SourceToken::new(
+                            Token::String(text.clone()),
+                            "",
+                            string.offset,
+                            string.line,
+                        ))),
+                        StringPart::Expression {
+                            source,
+                            offset,
+                            line,
+                        } => embedded_expression(source, *offset, *line),
+                    })
+                    .collect::<CrustCoreResult<_>>()?;
+                Ok(Expression::Interpolation {
+                    parts,
+                    line: string.line,
+                })
+            }
+            // `print` starts a statement, but elsewhere it names the native function.
+            Token::Print => {
+                let line = self.tokens.advance().line;
+                Ok(Expression::Variable {
+                    name: Symbol::intern("print"),
+                    line,
+                })
+            }
+            Token::Identifier(_) => {
+                let line = self.tokens.peek().line;
+                let name = self.identifier("Expected identifier")?;
+                if !self.tokens.check(TokenType::ColonColon) {
+                    return Ok(Expression::Variable { name, line });
+                }
+                let mut segments = vec![name];
+                while self.tokens.matches(&[TokenType::ColonColon]) {
+                    segments.push(self.identifier("Expected identifier after '::'")?);
+                }
+                Ok(Expression::Path { segments, line })
+            }
+            Token::LeftBrace => {
+                self.tokens.advance();
+                self.block_expression()
+            }
+            Token::Fn => {
+                self.tokens.advance();
+                self.lambda()
+            }
+            Token::LeftParen => {
+                self.tokens.advance();
+                let expr = self.expression()?;
+                if !self.tokens.matches(&[TokenType::RightParen]) {
+                    // Point at the end of the unclosed group, not at whatever follows it, which
+                    // may be lines further down.
+                    let last = self.tokens.previous();
+                    return Err(CrustCoreErr::Parse {
+                        kind: ErrorKind::Expected,
+                        line: last.line,
+                        message: "Expected ')' after expression".to_string(),
+                        span: Some(last.offset..last.offset + last.length),
+                    });
+                }
+                Ok(Expression::grouping(expr))
+            }
+            _ => Err(self
+                .tokens
+                .error(ErrorKind::Expected, "Expected expression")),
+        }
+    }
+
+    fn identifier(&mut self, message: &str) -> CrustCoreResult<Symbol> {
+        match self.tokens.peek().token {
+            Token::Identifier(name) => {
+                self.tokens.advance();
+                Ok(name)
+            }
+            _ => Err(self.tokens.error(ErrorKind::Expected, message)),
+        }
+    }
+
+    /// Consumes the `;`, or with newline terminators the line break, that ends a statement. A
+    /// statement at the end of a line may also directly precede a `}` or the end of the source.
+    fn terminator(&mut self, message: &str) -> CrustCoreResult {
+        if self.tokens.matches(&[TokenType::Semicolon])
+            || (self.newline_terminated && self.tokens.matches(&[TokenType::Newline]))
+            || (self.newline_terminated && self.at_terminator())
+        {
+            Ok(())
+        } else {
+            Err(self.tokens.error(ErrorKind::Expected, message))
+        }
+    }
+
+    /// Whether the next token ends a statement without being part of it.
+    fn at_terminator(&self) -> bool {
+        self.tokens.check(TokenType::Semicolon)
+            || (self.newline_terminated
+                && (self.tokens.check(TokenType::Newline)
+                    || self.tokens.check(TokenType::RightBrace)
+                    || self.tokens.is_at_end()))
+    }
+
+    /// Skips ahead to the next likely statement boundary so that one syntax error does not
+    /// cascade into a string of unrelated ones.
+    fn synchronize(&mut self) {
+        while !self.tokens.is_at_end() {
+            if matches!(
+                self.tokens.advance().token,
+                Token::Semicolon | Token::Newline
+            ) {
+                return;
+            }
+            match self.tokens.peek().token {
+                Token::Class
+                | Token::Fn
+                | Token::Let
+                | Token::Const
+                | Token::For
+                | Token::If
+                | Token::While
+                | Token::Loop
+                | Token::Print
+                | Token::Return => return,
+                _ => {}
+            }
+        }
+    }
+}
+
+fn is_comment(token: &SourceToken) -> bool {
+    matches!(token.token, Token::LineComment(_) | Token::BlockComment(_))
+}
+
+/// Parses the source of a `${...}` in a string as a single expression, with its tokens and
+/// errors placed where the expression is in the whole source, which starts at byte `offset` of
+/// line `line`.
+fn embedded_expression(source: &str, offset: usize, line: usize) -> CrustCoreResult<Expression> {
+    let (mut tokens, errors) = Scanner::new(source).scan();
+    if !errors.is_empty() {
+        let errors = errors
+            .into_iter()
+            .map(|error| match error {
+                CrustCoreErr::Scan {
+                    kind,
+                    line: error_line,
+                    offset: error_offset,
+                    message,
+                } => CrustCoreErr::Scan {
+                    kind,
+                    line: error_line + line - 1,
+                    offset: error_offset + offset,
+                    message,
+                },
+                other => other,
+            })
+            .collect();
+        return Err(CrustCoreErr::Multi { errors });
+    }
+    for token in &mut tokens {
+        token.offset += offset;
+        token.line += line - 1;
+    }
+    let mut parser = Parser::new(tokens);
+    let expr = parser.expression()?;
+    if !parser.tokens.is_at_end() {
+        return Err(parser.tokens.error(
+            ErrorKind::Expected,
+            "Expected '}' after interpolated expression",
+        ));
+    }
+    Ok(expr)
+}
+
+/// Keeps the `Newline`s that end a statement, dropping those inside parentheses or brackets,
+/// after a token that cannot end a statement such as an operator or `{`, before a token that
+/// cannot start one such as `*` or `else`, before a `}` or the end, and all but one of a run.
+fn terminating_newlines(tokens: Vec<SourceToken>) -> Vec<SourceToken> {
+    let mut kept: Vec<SourceToken> = Vec::with_capacity(tokens.len());
+    let mut groups = vec![];
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        match token.token {
+            Token::LeftParen | Token::LeftBracket | Token::LeftBrace => {
+                groups.push(token.token.clone())
+            }
+            Token::RightParen | Token::RightBracket | Token::RightBrace => {
+                groups.pop();
+            }
+            Token::Newline => {
+                let in_parens =
+                    matches!(groups.last(), Some(Token::LeftParen | Token::LeftBracket));
+                let ends_statement = kept.last().is_some_and(|last| ends_statement(&last.token));
+                let continues = tokens.peek().is_none_or(|next| continues_line(&next.token));
+                if in_parens || !ends_statement || continues {
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        kept.push(token);
+    }
+    kept
+}
+
+/// Whether a line ending in `token` can be a complete statement.
+fn ends_statement(token: &Token) -> bool {
+    matches!(
+        token.kind(),
+        TokenType::Identifier
+            | TokenType::String
+            | TokenType::Interpolation
+            | TokenType::Char
+            | TokenType::Integer
+            | TokenType::Float
+            | TokenType::True
+            | TokenType::False
+            | TokenType::Nil
+            | TokenType::This
+            | TokenType::Super
+            | TokenType::Break
+            | TokenType::Return
+            | TokenType::RightParen
+            | TokenType::RightBracket
+            | TokenType::RightBrace
+            | TokenType::PlusPlus
+            | TokenType::MinusMinus
+    )
+}
+
+/// Whether a line starting with `token` must continue the previous one, because `token`
+/// cannot begin a statement or is a `}` or the end.
+fn continues_line(token: &Token) -> bool {
+    matches!(
+        token.kind(),
+        TokenType::Star
+            | TokenType::StarStar
+            | TokenType::Slash
+            | TokenType::Percent
+            | TokenType::EqualEqual
+            | TokenType::BangEqual
+            | TokenType::Less
+            | TokenType::LessEqual
+            | TokenType::Greater
+            | TokenType::GreaterEqual
+            | TokenType::BitAnd
+            | TokenType::BitOr
+            | TokenType::And
+            | TokenType::Or
+            | TokenType::QuestionQuestion
+            | TokenType::Question
+            | TokenType::Colon
+            | TokenType::ColonColon
+            | TokenType::Dot
+            | TokenType::DotDot
+            | TokenType::DotDotEqual
+            | TokenType::Arrow
+            | TokenType::Equal
+            | TokenType::Comma
+            | TokenType::Else
+            | TokenType::RightBrace
+            | TokenType::Newline
+            | TokenType::Eof
+    )
+}
+
+/// Widens the span of an error in the right operand of `op` to start at `op`, so that e.g.
+/// `1 * * 2` underlines `* *` rather than only the second `*`.
+fn from_operator(err: CrustCoreErr, op: &SourceToken) -> CrustCoreErr {
+    match err {
+        CrustCoreErr::Parse {
+            kind,
+            line,
+            message,
+            span: Some(span),
+        } => CrustCoreErr::Parse {
+            kind,
+            line,
+            message,
+            span: Some(op.offset.min(span.start)..span.end),
+        },
+        err => err,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ast::{AstPrinter, SourcePrinter},
+        fold::strip_groupings,
+    };
+
+    fn parse(source: &str) -> CrustCoreResult<Expression> {
+        let tokens = Scanner::new(source).scan_tokens()?;
+        Parser::new(tokens).expression()
+    }
+
+    fn parse_program(source: &str) -> CrustCoreResult<Vec<Statement>> {
+        let tokens = Scanner::new(source).scan_tokens()?;
+        Parser::new(tokens).parse_program()
+    }
+
+    #[test]
+    fn parse_logical_precedence() {
+        let expr = parse("true or false and true").unwrap();
+        assert_eq!(
+            expr.accept(&AstPrinter {}).unwrap(),
+            "( or true ( and false true ) )"
+        );
+    }
+
+    #[test]
+    fn parse_logical_below_equality() {
+        let expr = parse("1 == 1 and 2 != 3").unwrap();
+        assert_eq!(
+            expr.accept(&AstPrinter {}).unwrap(),
+            "( and ( == 1 1 ) ( != 2 3 ) )"
+        );
+    }
+
+    #[test]
+    fn parse_coalesce_below_logical() {
+        let expr = parse("a ?? b or c ?? d").unwrap();
+        assert_eq!(
+            expr.accept(&AstPrinter {}).unwrap(),
+            "( ?? ( ?? a ( or b c ) ) d )"
+        );
+    }
+
+    #[test]
+    fn parse_conditional() {
+        let expr = parse("a ? b : c").unwrap();
+        assert_eq!(expr.accept(&AstPrinter {}).unwrap(), "( ? a b c )");
+    }
+
+    #[test]
+    fn parse_power() {
+        let print = |source| parse(source).unwrap().accept(&AstPrinter {}).unwrap();
+        assert_eq!(print("a ** b ** c"), "( ** a ( ** b c ) )");
+        assert_eq!(print("-a ** b"), "( - ( ** a b ) )");
+        assert_eq!(print("a ** -b * c"), "( * ( ** a ( - b ) ) c )");
+        assert_eq!(print("f(x) ** 2"), "( ** ( call f x ) 2 )");
+        let source = |source| {
+            strip_groupings(&parse(source).unwrap())
+                .accept(&SourcePrinter {})
+                .unwrap()
+        };
+        assert_eq!(source("(a ** b) ** c"), "(a ** b) ** c");
+        assert_eq!(source("a ** (b ** c)"), "a ** b ** c");
+        assert_eq!(source("(-a) ** b"), "(-a) ** b");
+        assert!(matches!(parse("a **"), Err(CrustCoreErr::Parse { .. })));
+    }
+
+    #[test]
+    fn parse_conditional_precedence() {
+        let printed = |source| parse(source).unwrap().accept(&AstPrinter {}).unwrap();
+        assert_eq!(printed("a ? b : c ? d : e"), "( ? a b ( ? c d e ) )");
+        assert_eq!(printed("x = a ? b : c"), "( = x ( ? a b c ) )");
+        assert_eq!(
+            printed("x = a ? b : c ? d : e"),
+            "( = x ( ? a b ( ? c d e ) ) )"
+        );
+        assert_eq!(
+            printed("a or b ? c ?? d : e"),
+            "( ? ( or a b ) ( ?? c d ) e )"
+        );
+        assert_eq!(
+            printed("(a ? b : c) ? d : e"),
+            "( ? ( group ( ? a b c ) ) d e )"
+        );
+        assert_eq!(printed("a ? b = c : d"), "( ? a ( = b c ) d )");
+        assert_eq!(printed("a ? b : (c = d)"), "( ? a b ( group ( = c d ) ) )");
+        match parse("a ? b : c = d") {
+            Err(CrustCoreErr::Parse { message, .. }) => {
+                assert_eq!(message, "Invalid assignment target")
+            }
+            other => panic!("Expected a Parse error, got {other:?}"),
+        }
+        assert!(matches!(parse("a ? b"), Err(CrustCoreErr::Parse { .. })));
+    }
+
+    #[test]
+    fn duplicate_parameters_are_an_error() {
+        // The rest of the function is still parsed, so its body and the code after it do not
+        // add errors of their own.
+        for source in [
+            "fn f(a, b,\n a) { return a; }\nprint f(1, 2, 3);",
+            "let f = fn(a, b,\n a) { return a; };\nprint f(1, 2, 3);",
+            "fn f(a, _, _,\n a, a) {}",
+        ] {
+            match parse_program(source) {
+                Err(CrustCoreErr::Multi { errors }) => match &errors[..] {
+                    [CrustCoreErr::Parse {
+                        line,
+                        message,
+                        span,
+                        ..
+                    }] => {
+                        assert_eq!(message, "Duplicate parameter 'a'");
+                        assert_eq!(*line, 2);
+                        assert_eq!(span.clone().map(|span| &source[span]), Some("a"));
+                    }
+                    other => panic!("Expected one Parse error, got {other:?}"),
+                },
+                other => panic!("Expected a Parse error for {source}, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn distinct_parameters_are_accepted() {
+        let program = parse_program("fn f(a, b, c) {}\nlet g = fn(a, ab) { a };").unwrap();
+        match &program[..] {
+            [Statement::Function { params, .. }, Statement::Let {
+                initializer: Some(Expression::Lambda { params: lambda, .. }),
+                ..
+            }] => {
+                assert_eq!(params.len(), 3);
+                assert_eq!(lambda.len(), 2);
+            }
+            other => panic!("Expected a function and a lambda, got {other:?}"),
+        }
+        // The discard `_` may be repeated.
+        let program = parse_program("fn f(_, _) {}\nlet g = fn(_, a, _) { a };").unwrap();
+        match &program[..] {
+            [Statement::Function { params, .. }, Statement::Let {
+                initializer: Some(Expression::Lambda { params: lambda, .. }),
+                ..
+            }] => {
+                assert_eq!(params.len(), 2);
+                assert_eq!(lambda.len(), 3);
+            }
+            other => panic!("Expected a function and a lambda, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_chained_unary() {
+        let printed = |source| parse(source).unwrap().accept(&AstPrinter {}).unwrap();
+        assert_eq!(printed("!!true"), "( ! ( ! true ) )");
+        assert_eq!(printed("- -5"), "( - ( - 5 ) )");
+        assert_eq!(printed("-+1 * 2"), "( * ( - ( + 1 ) ) 2 )");
+    }
+
+    #[test]
+    fn parse_grouping() {
+        let expr = parse("(1 + 2) * 3").unwrap();
+        assert_eq!(
+            expr.accept(&AstPrinter {}).unwrap(),
+            "( * ( group ( + 1 2 ) ) 3 )"
+        );
+    }
+
+    #[test]
+    fn parse_unclosed_grouping() {
+        match parse("(1 +\n2\n\n") {
+            Err(CrustCoreErr::Parse { line, message, .. }) => {
+                assert_eq!(line, 2);
+                assert_eq!(message, "Expected ')' after expression");
+            }
+            other => panic!("Expected a Parse error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_nil_literal() {
+        assert_eq!(
+            parse("nil").unwrap(),
+            Expression::Literal {
+                value: SourceToken {
+                    column: 1,
+                    ..SourceToken::new(Token::Nil, "nil", 0, 1)
+                }
+            }
+        );
+    }
+
+    #[test]
+    fn parse_missing_operand() {
+        assert!(matches!(
+            parse("true and"),
+            Err(CrustCoreErr::Parse { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn parse_const_declaration() {
+        let statements = parse_program("const A = B + 2;").unwrap();
+        assert_eq!(
+            statements[0].accept(&AstPrinter {}).unwrap(),
+            "( const A ( + B 2 ) ) ;"
+        );
+        assert!(matches!(
+            parse_program("const A;"),
+            Err(CrustCoreErr::Multi { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_for_loop() {
+        let statements = parse_program("for i in 0..n + 1 { print i; }").unwrap();
+        assert_eq!(
+            statements[0].accept(&AstPrinter {}).unwrap(),
+            "( for i ( .. 0 ( + n 1 ) ) { ( print i ) ; } )"
+        );
+        assert!(matches!(
+            parse_program("for i in 3 { }"),
+            Err(CrustCoreErr::Multi { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_annotated_function() {
+        let statements = parse_program("fn f(a: Int, b) -> Int { return a; }").unwrap();
+        assert_eq!(
+            statements[0].accept(&AstPrinter {}).unwrap(),
+            "( fn f ( a: Int b ) -> Int { ( return a ) ; } )"
+        );
+        assert!(matches!(
+            parse_program("fn f() -> { }"),
+            Err(CrustCoreErr::Multi { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_block_expression() {
+        let statements = parse_program("let x = { let a = 2; a + 1 }; let y = { x; };").unwrap();
+        let printed = statements
+            .iter()
+            .map(|statement| statement.accept(&AstPrinter {}).unwrap())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            printed,
+            vec![
+                "( let x { ( let a 2 ) ; ( + a 1 ) } ) ;",
+                "( let y { x ; } ) ;"
+            ]
+        );
+        assert!(matches!(
+            parse_program("let x = { 1 2 };"),
+            Err(CrustCoreErr::Multi { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_statements() {
+        let statements = parse_program("print 1; true;").unwrap();
+        let printed = statements
+            .iter()
+            .map(|statement| statement.accept(&AstPrinter {}).unwrap())
+            .collect::<Vec<String>>();
+        assert_eq!(printed, vec!["( print 1 ) ;", "true ;"]);
+    }
+
+    #[test]
+    fn parse_program_recovers_after_error() {
+        match parse_program("print ; print 1 print 2;") {
+            Err(CrustCoreErr::Multi { errors }) => assert_eq!(errors.len(), 2),
+            _ => panic!("Expected multiple parse errors"),
+        }
+    }
+
+    #[test]
+    fn parse_error_spans_operator_and_operand() {
+        let source = "print 1 * * 2;";
+        let err = parse_program(source).unwrap_err();
+        assert_eq!(
+            err.render(source),
+            "[line 1] Error[E0101]: Expected expression\n    print 1 * * 2;\n            ^^^"
+        );
+        let Err(CrustCoreErr::Parse { span, .. }) = parse("1 +\n)") else {
+            panic!("Expected a Parse error");
+        };
+        assert_eq!(span, Some(2..5));
+    }
+
+    #[test]
+    fn parse_function_declaration() {
+        let statements = parse_program("fn add(a, b) { return a + b; } add(1, 2);").unwrap();
+        let printed = statements
+            .iter()
+            .map(|statement| statement.accept(&AstPrinter {}).unwrap())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            printed,
+            vec![
+                "( fn add ( a b ) { ( return ( + a b ) ) ; } )",
+                "( call add 1 2 ) ;"
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_empty_source() {
+        assert_eq!(parse_program("").unwrap(), vec![]);
+        assert_eq!(parse_program("  // nothing\n").unwrap(), vec![]);
+        assert_eq!(Parser::new(vec![]).parse_program().unwrap(), vec![]);
+        assert!(matches!(
+            parse(""),
+            Err(CrustCoreErr::Parse { line: 1, message, .. })
+                if message == "Unexpected end of input: Expected expression"
+        ));
+    }
+
+    #[test]
+    fn truncated_input_errors_at_eof() {
+        match parse("1 +") {
+            Err(CrustCoreErr::Parse { message, span, .. }) => {
+                assert_eq!(message, "Unexpected end of input: Expected expression");
+                assert_eq!(span, Some(2..3));
+            }
+            other => panic!("Expected a Parse error, got {other:?}"),
+        }
+        assert_eq!(
+            parse_program("fn f(a, b").unwrap_err().to_string(),
+            "[line 1] Error: Unexpected end of input: Expected ')' after parameters"
+        );
+    }
+
+    fn parse_lines(source: &str) -> CrustCoreResult<Vec<String>> {
+        let tokens = Scanner::new(source).emit_newlines(true).scan_tokens()?;
+        Parser::with_newlines(tokens)
+            .parse_program()?
+            .iter()
+            .map(|statement| statement.accept(&AstPrinter {}))
+            .collect()
+    }
+
+    #[test]
+    fn newlines_terminate_statements() {
+        assert_eq!(
+            parse_lines("let x = a\nprint x\n").unwrap(),
+            vec!["( let x a ) ;", "( print x ) ;"]
+        );
+        assert_eq!(
+            parse_lines("\nfn f(a) {\n  return a\n}\n\nf(1); f(2)").unwrap(),
+            vec![
+                "( fn f ( a ) { ( return a ) ; } )",
+                "( call f 1 ) ;",
+                "( call f 2 ) ;"
+            ]
+        );
+        assert!(parse_lines("print a b\n").is_err());
+        // Without the parser mode, line breaks are not terminators.
+        let tokens = Scanner::new("print a\n")
+            .emit_newlines(true)
+            .scan_tokens()
+            .unwrap();
+        assert!(Parser::new(tokens).parse_program().is_err());
+    }
+
+    #[test]
+    fn operators_continue_lines() {
+        assert_eq!(
+            parse_lines("let x = a +\n  b\nprint x").unwrap(),
+            vec!["( let x ( + a b ) ) ;", "( print x ) ;"]
+        );
+        assert_eq!(
+            parse_lines("let y = a\n  * b\n  and c\ny").unwrap(),
+            vec!["( let y ( and ( * a b ) c ) ) ;", "y ;"]
+        );
+        assert_eq!(
+            parse_lines("f(a,\n  b\n)").unwrap(),
+            vec!["( call f a b ) ;"]
+        );
+        // `-` can start a statement, so a line starting with one does not continue.
+        assert_eq!(parse_lines("a\n-b").unwrap(), vec!["a ;", "( - b ) ;"]);
+    }
+
+    #[test]
+    fn lenient_parse_keeps_the_good_statements() {
+        let tokens = Scanner::new("let a = 1;\nprint (2;\nprint a;")
+            .scan_tokens()
+            .unwrap();
+        let (statements, errors) = Parser::new(tokens).parse_program_lenient();
+        let printed = statements
+            .iter()
+            .map(|statement| statement.accept(&AstPrinter {}).unwrap())
+            .collect::<Vec<String>>();
+        assert_eq!(printed, vec!["( let a 1 ) ;", "( print a ) ;"]);
+        match &errors[..] {
+            [CrustCoreErr::Parse { line: 2, .. }] => {}
+            other => panic!("Expected one error on line 2, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_let_annotation() {
+        let statements = parse_program("let mut x: Int = 1; let s: Str;").unwrap();
+        let printed = statements
+            .iter()
+            .map(|statement| statement.accept(&AstPrinter {}).unwrap())
+            .collect::<Vec<String>>();
+        assert_eq!(printed, vec!["( let mut x: Int 1 ) ;", "( let s: Str ) ;"]);
+        assert!(parse_program("let x: = 1;").is_err());
+    }
+
+    #[test]
+    fn parse_interpolation() {
+        let expr = parse("\"a ${b + 1} c\"").unwrap();
+        assert_eq!(
+            expr.accept(&AstPrinter {}).unwrap(),
+            "( interpolate \"a \" ( + b 1 ) \" c\" )"
+        );
+        assert_eq!(expr.accept(&SourcePrinter {}).unwrap(), "\"a ${b + 1} c\"");
+        match parse("\"${x}\"") {
+            Ok(Expression::Interpolation { parts, .. }) => match &parts[..] {
+                [Expression::Variable { line: 1, .. }] => {}
+                other => panic!("Expected one variable, found {other:?}"),
+            },
+            other => panic!("Expected an interpolation, found {other:?}"),
+        }
+        assert!(matches!(
+            parse("\"${1 2}\""),
+            Err(CrustCoreErr::Parse { .. })
+        ));
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error() {
+        let depth = 100_000;
+        let source = format!("{}1{};", "(".repeat(depth), ")".repeat(depth));
+        match parse_program(&source) {
+            Err(CrustCoreErr::Multi { errors }) => match &errors[..] {
+                [CrustCoreErr::Parse { message, .. }] => {
+                    assert_eq!(message, "Maximum nesting depth exceeded")
+                }
+                other => panic!("Expected one Parse error, got {other:?}"),
+            },
+            other => panic!("Expected a nesting error, got {other:?}"),
+        }
+        let blocks = format!("{}{}", "{".repeat(depth), "}".repeat(depth));
+        assert!(parse_program(&blocks).is_err());
+        let negations = format!("print {}true;", "!".repeat(depth));
+        assert!(parse_program(&negations).is_err());
+
+        let tokens = Scanner::new("((1));").scan_tokens().unwrap();
+        assert!(Parser::new(tokens.clone())
+            .with_max_depth(3)
+            .parse_program()
+            .is_ok());
+        assert!(Parser::new(tokens)
+            .with_max_depth(2)
+            .parse_program()
+            .is_err());
+    }
+
+    #[test]
+    fn long_chains_are_not_nesting() {
+        let error = |source: &str| match parse_program(source) {
+            Err(CrustCoreErr::Multi { errors }) => match &errors[..] {
+                [CrustCoreErr::Parse { message, .. }] => message.clone(),
+                other => panic!("Expected one Parse error, got {other:?}"),
+            },
+            other => panic!("Expected a Parse error, got {other:?}"),
+        };
+        let chain = |operand: &str, op: &str, length| vec![operand; length].join(op);
+        for source in [
+            chain("1", " + ", 1_000),
+            chain("\"a\"", " + ", 1_000),
+            chain("true", " and ", 1_000),
+            chain("nil", " ?? ", 1_000),
+            format!("({}) + {}", chain("1", "+", 2_000), chain("1", "+", 2_000)),
+        ] {
+            let tokens = Scanner::new(&format!("print {source};"))
+                .scan_tokens()
+                .unwrap();
+            let parser = Parser::new(tokens).with_max_depth(2);
+            assert!(parser.parse_program().is_ok(), "{source}");
+        }
+
+        // A chain still nests the tree, so there is a limit on the operators of all the
+        // chains around a token, though far beyond what any real code needs.
+        let too_long = "Too many operators in one expression";
+        assert_eq!(
+            error(&format!("print {};", chain("1", "+", 200_000))),
+            too_long
+        );
+        assert_eq!(
+            error(&format!(
+                "print {} + ({});",
+                chain("1", "+", 3_000),
+                chain("1", "+", 3_000)
+            )),
+            too_long
+        );
+        assert!(parse_program(&format!("print {};", chain("1", "+", 4_097))).is_ok());
+        assert_eq!(
+            error(&format!("print {};", chain("1", "+", 4_098))),
+            too_long
+        );
+
+        // Calls and indexing each nest a level.
+        let nesting = "Maximum nesting depth exceeded";
+        assert_eq!(error(&format!("f{};", "()".repeat(10_000))), nesting);
+        assert_eq!(error(&format!("s{};", "[0]".repeat(10_000))), nesting);
+        let tokens = Scanner::new("f()();").scan_tokens().unwrap();
+        assert!(Parser::new(tokens.clone())
+            .with_max_depth(3)
+            .parse_program()
+            .is_ok());
+        assert!(Parser::new(tokens)
+            .with_max_depth(2)
+            .parse_program()
+            .is_err());
+    }
+
+    #[test]
+    fn parse_path() {
+        match parse("a::b::c").unwrap() {
+            Expression::Path { segments, line: 1 } => assert_eq!(
+                segments,
+                vec![
+                    Symbol::intern("a"),
+                    Symbol::intern("b"),
+                    Symbol::intern("c")
+                ]
+            ),
+            other => panic!("Expected a path, found {other:?}"),
+        }
+        assert!(matches!(parse("a"), Ok(Expression::Variable { .. })));
+        assert!(matches!(parse("a::"), Err(CrustCoreErr::Parse { .. })));
+    }
+
+    #[test]
+    fn parse_lambda() {
+        let expr = parse("fn(a, b: Int) { let c = a; c + b }").unwrap();
+        assert_eq!(
+            expr.accept(&AstPrinter {}).unwrap(),
+            "( fn ( a b: Int ) { ( let c a ) ; ( return ( + c b ) ) ; } )"
+        );
+        assert_eq!(
+            expr.accept(&SourcePrinter {}).unwrap(),
+            "fn(a, b: Int) { let c = a; return c + b; }"
+        );
+        assert_eq!(
+            parse("fn() { print 1; }(2)")
+                .unwrap()
+                .accept(&AstPrinter {})
+                .unwrap(),
+            "( call ( fn (  ) { ( print 1 ) ; } ) 2 )"
+        );
+        // At the start of a statement, `fn` followed by a name still declares a function.
+        assert!(matches!(
+            parse_program("fn f() {}\nfn() {};").unwrap().as_slice(),
+            [
+                Statement::Function { .. },
+                Statement::Expression {
+                    expr: Expression::Lambda { .. }
+                }
+            ]
+        ));
+        assert!(matches!(
+            parse("fn f() {}"),
+            Err(CrustCoreErr::Parse { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_index() {
+        let expr = parse("s[i + 1](2)[0]").unwrap();
+        assert_eq!(
+            expr.accept(&AstPrinter {}).unwrap(),
+            "( index ( call ( index s ( + i 1 ) ) 2 ) 0 )"
+        );
+        assert!(matches!(parse("s[0"), Err(CrustCoreErr::Parse { .. })));
+    }
+
+    #[test]
+    fn parse_trailing_commas() {
+        let print = |source| {
+            parse_program(source)
+                .unwrap()
+                .iter()
+                .map(|statement| statement.accept(&AstPrinter {}).unwrap())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(
+            print("fn g(a, b,) {}\nf(a,\n  b,\n);"),
+            vec!["( fn g ( a b ) { } )", "( call f a b ) ;"]
+        );
+        assert_eq!(print("f(a,);"), vec!["( call f a ) ;"]);
+        for source in ["f(,a);", "f(a,,b);", "f(,);", "fn g(,a) {}", "fn g(a,,) {}"] {
+            assert!(parse_program(source).is_err(), "{source}");
+        }
+    }
+
+    #[test]
+    fn parse_assignment_is_right_associative() {
+        let expr = parse("a = b = 1").unwrap();
+        assert_eq!(expr.accept(&AstPrinter {}).unwrap(), "( = a ( = b 1 ) )");
+        assert!(matches!(parse("1 = 2"), Err(CrustCoreErr::Parse { .. })));
+    }
+
+    #[test]
+    fn parse_top_level_return() {
+        assert!(parse_program("return 1;").is_err());
+    }
+}
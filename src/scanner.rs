@@ -1,7 +1,10 @@
-use crust_grammar::token::{try_as_keyword, SourceToken, Token};
-use std::str::FromStr;
+use crust_grammar::{
+    symbol::Symbol,
+    token::{try_as_keyword, BorrowedToken, SourceToken, StringPart, Token},
+};
+use std::{borrow::Cow, num::IntErrorKind, str::FromStr};
 
-use crate::util::{CrustCoreErr, CrustCoreResult};
+use crate::util::{CrustCoreErr, CrustCoreResult, ErrorKind};
 
 pub struct Scanner<'a> {
     source: &'a str,
@@ -10,7 +13,56 @@ pub struct Scanner<'a> {
     current: usize,
     line: usize,
 
-    tokens: Vec<SourceToken>,
+    /// How many columns a tab advances to, as a tab stop.
+    tab_width: usize,
+    /// A byte offset whose column is known, from which later columns are counted.
+    column_from: (usize, usize),
+    /// Whether line breaks are scanned as `Newline` tokens rather than skipped.
+    emit_newlines: bool,
+    /// Whether comments are scanned as `LineComment` and `BlockComment` tokens.
+    keep_comments: bool,
+
+    tokens: Vec<BorrowedToken<'a>>,
+}
+
+/// The text of a run of a string literal, which borrows its source until an escape needs
+/// decoding, and only then is copied out.
+struct Text {
+    /// Where in the source the run starts.
+    start: usize,
+    decoded: Option<String>,
+}
+
+impl Text {
+    fn new(start: usize) -> Self {
+        Self {
+            start,
+            decoded: None,
+        }
+    }
+
+    /// Adds a character that stands for itself in the source.
+    fn push(&mut self, char: char) {
+        if let Some(decoded) = &mut self.decoded {
+            decoded.push(char);
+        }
+    }
+
+    /// Adds the decoded character of the escape starting at byte `escape`.
+    fn decode(&mut self, source: &str, escape: usize, char: char) {
+        self.decoded
+            .get_or_insert_with(|| source[self.start..escape].to_string())
+            .push(char);
+    }
+
+    /// The text of the run up to byte `end`, or `None` if it is empty.
+    fn take<'a>(&mut self, source: &'a str, end: usize) -> Option<Cow<'a, str>> {
+        let text = match self.decoded.take() {
+            Some(decoded) => Cow::Owned(decoded),
+            None => Cow::Borrowed(&source[self.start..end]),
+        };
+        (!text.is_empty()).then_some(text)
+    }
 }
 
 impl<'a> Scanner<'a> {
@@ -20,27 +72,112 @@ impl<'a> Scanner<'a> {
             start: 0,
             current: 0,
             line: 1,
+            tab_width: 1,
+            column_from: (0, 1),
+            emit_newlines: false,
+            keep_comments: false,
             tokens: vec![],
         }
     }
 
-    pub fn scan_tokens(mut self) -> CrustCoreResult<Vec<SourceToken>> {
+    /// Counts tab characters up to the next multiple of `tab_width` columns when computing token
+    /// columns, e.g. 4 or 8 to line up with a terminal. The default of 1 counts a tab as one
+    /// character, like any other. Byte offsets are unaffected.
+    pub fn with_tab_width(mut self, tab_width: usize) -> Self {
+        self.tab_width = tab_width.max(1);
+        self
+    }
+
+    /// Scans each line break outside of strings and comments as a `Newline`, for
+    /// [`Parser::with_newlines`](crate::parser::Parser::with_newlines).
+    pub fn emit_newlines(mut self, emit_newlines: bool) -> Self {
+        self.emit_newlines = emit_newlines;
+        self
+    }
+
+    /// Scans comments as `LineComment` and `BlockComment` tokens rather than skipping them, for
+    /// tools that need to give them back, such as a formatter. The parser ignores them.
+    pub fn keep_comments(mut self, keep_comments: bool) -> Self {
+        self.keep_comments = keep_comments;
+        self
+    }
+
+    pub fn scan_tokens(self) -> CrustCoreResult<Vec<SourceToken>> {
+        let (tokens, errors) = self.scan();
+        if !errors.is_empty() {
+            Err(CrustCoreErr::Multi { errors })
+        } else {
+            Ok(tokens)
+        }
+    }
+
+    /// Scans the whole source, returning every token that could be scanned, always ending in
+    /// `Eof`, alongside the errors for the parts that could not.
+    pub fn scan(self) -> (Vec<SourceToken>, Vec<CrustCoreErr>) {
+        let (tokens, errors) = self.scan_borrowed();
+        let tokens = tokens.into_iter().map(BorrowedToken::into_owned).collect();
+        (tokens, errors)
+    }
+
+    /// [`scan`](Self::scan), but with lexemes and payloads borrowed from the source rather than
+    /// copied, for tools that only look at the tokens while the source is around.
+    pub fn scan_borrowed(mut self) -> (Vec<BorrowedToken<'a>>, Vec<CrustCoreErr>) {
         let mut errors: Vec<CrustCoreErr> = vec![];
+        self.skip_shebang();
         while !self.is_at_end() {
             self.start = self.current;
             self.scan_token(&mut errors);
         }
 
-        self.tokens
-            .push(SourceToken::new(Token::Eof, self.current, self.line, 0));
+        self.start = self.current;
+        self.push_token(Token::Eof);
+        (self.tokens, errors)
+    }
 
-        if !errors.is_empty() {
-            Err(CrustCoreErr::Multi { errors })
-        } else {
-            Ok(self.tokens)
+    /// Whether the source stops in the middle of a construct, i.e. inside a string literal or
+    /// with unclosed parentheses or braces, so that an interactive prompt should keep reading.
+    pub fn needs_more_input(mut self) -> bool {
+        let mut errors: Vec<CrustCoreErr> = vec![];
+        while !self.is_at_end() {
+            self.start = self.current;
+            self.scan_token(&mut errors);
+            if let Some(CrustCoreErr::Scan {
+                kind: ErrorKind::UnterminatedString | ErrorKind::UnterminatedComment,
+                ..
+            }) = errors.last()
+            {
+                return true;
+            }
+        }
+
+        let mut depth = 0;
+        for source_token in &self.tokens {
+            match source_token.token {
+                Token::LeftParen | Token::LeftBrace | Token::LeftBracket => depth += 1,
+                Token::RightParen | Token::RightBrace | Token::RightBracket => depth -= 1,
+                _ => {}
+            }
+        }
+        depth > 0
+    }
+
+    /// Skips a `#!/usr/bin/env crust` line at the very start of the source, so scripts can be run
+    /// directly. A `#` anywhere else is still an unexpected character.
+    fn skip_shebang(&mut self) {
+        if self.source.starts_with("#!") {
+            self.skip_line();
         }
     }
 
+    /// Advances to the line break ending the current line, or to the end of the source. Line
+    /// breaks are ASCII, so the line can be skipped bytewise.
+    fn skip_line(&mut self) {
+        self.current += self.source.as_bytes()[self.current..]
+            .iter()
+            .position(|byte| matches!(byte, b'\n' | b'\r'))
+            .unwrap_or(self.source.len() - self.current);
+    }
+
     fn scan_token(&mut self, errors: &mut Vec<CrustCoreErr>) {
         let char = self.advance();
         match char {
@@ -48,12 +185,26 @@ impl<'a> Scanner<'a> {
             ')' => self.push_token(Token::RightParen),
             '{' => self.push_token(Token::LeftBrace),
             '}' => self.push_token(Token::RightBrace),
+            '[' => self.push_token(Token::LeftBracket),
+            ']' => self.push_token(Token::RightBracket),
             ',' => self.push_token(Token::Comma),
+            '.' if self.advance_if('.') => {
+                if self.advance_if('=') {
+                    self.push_token(Token::DotDotEqual);
+                } else {
+                    self.push_token(Token::DotDot);
+                }
+            }
             '.' => self.push_token(Token::Dot),
+            '-' if self.advance_if('-') => self.push_token(Token::MinusMinus),
+            '-' if self.advance_if('>') => self.push_token(Token::Arrow),
             '-' => self.push_token(Token::Minus),
+            '+' if self.advance_if('+') => self.push_token(Token::PlusPlus),
             '+' => self.push_token(Token::Plus),
             ';' => self.push_token(Token::Semicolon),
+            '*' if self.advance_if('*') => self.push_token(Token::StarStar),
             '*' => self.push_token(Token::Star),
+            '%' => self.push_token(Token::Percent),
             '!' if self.advance_if('=') => {
                 self.push_token(Token::BangEqual);
             }
@@ -78,46 +229,109 @@ impl<'a> Scanner<'a> {
             '>' => {
                 self.push_token(Token::Greater);
             }
-            '/' => {
-                if self.advance_if('/') {
-                    while self.peek() != '\n' && !self.is_at_end() {
-                        self.advance();
-                    }
-                } else {
-                    self.push_token(Token::Slash);
+            '?' if self.advance_if('?') => {
+                self.push_token(Token::QuestionQuestion);
+            }
+            '?' => self.push_token(Token::Question),
+            ':' if self.advance_if(':') => self.push_token(Token::ColonColon),
+            ':' => self.push_token(Token::Colon),
+            '&' if self.advance_if('&') => self.push_token(Token::And),
+            '&' => self.push_token(Token::BitAnd),
+            '|' if self.advance_if('|') => self.push_token(Token::Or),
+            '|' => self.push_token(Token::BitOr),
+            '/' if self.advance_if('/') => {
+                self.skip_line();
+                if self.keep_comments {
+                    let text = &self.source[self.start + 2..self.current];
+                    self.push_token(Token::LineComment(Cow::Borrowed(text)));
                 }
             }
+            '/' if self.advance_if('*') => match self.take_block_comment() {
+                Ok(text) if self.keep_comments => {
+                    self.push_token(Token::BlockComment(Cow::Borrowed(text)))
+                }
+                Ok(_) => {}
+                Err(e) => errors.push(e),
+            },
+            '/' => self.push_token(Token::Slash),
             '0'..='9' => {
                 if let Err(e) = self.take_number_literal() {
                     errors.push(e);
                 }
             }
-            'A'..='z' => {
+            'a'..='z' | 'A'..='Z' | '_' => {
                 if let Err(e) = self.take_identifier() {
                     errors.push(e);
                 }
             }
-            ' ' | '\t' | '\r' => {}
-            '\n' => self.line += 1,
+            ' ' | '\t' => {}
+            // `\r\n` counts once, at its `\n`, while a lone `\r` ends a line by itself.
+            '\r' if self.peek() == '\n' => {}
+            '\r' | '\n' => {
+                if self.emit_newlines {
+                    self.push_token(Token::Newline);
+                }
+                self.line += 1;
+            }
             '\"' => {
                 if let Err(e) = self.take_string_literal() {
                     errors.push(e);
                 }
             }
-            _ => errors.push(CrustCoreErr::Scan {
-                line: self.line,
-                message: "Unexpected character".to_string(),
-            }),
+            '#' if self.at_line_start() && self.source[self.current..].starts_with("line") => {
+                if let Err(e) = self.take_line_directive() {
+                    errors.push(e);
+                }
+            }
+            '\'' => {
+                if let Err(e) = self.take_char_literal() {
+                    errors.push(e);
+                }
+            }
+            char => errors.push(self.error(
+                ErrorKind::UnexpectedCharacter,
+                format!("Unexpected character '{char}'"),
+            )),
         }
     }
 
-    fn push_token(&mut self, token: Token) {
-        self.tokens.push(SourceToken::new(
+    fn push_token(&mut self, token: Token<'a>) {
+        let column = self.column_at(self.start);
+        self.tokens.push(BorrowedToken {
             token,
-            self.start,
-            self.line,
-            self.current - self.start,
-        ))
+            lexeme: &self.source[self.start..self.current],
+            offset: self.start,
+            line: self.line,
+            column,
+        })
+    }
+
+    /// The column of byte `offset`, counted on from the previous token so that each part of the
+    /// source is only walked once.
+    fn column_at(&mut self, offset: usize) -> usize {
+        let (from, mut column) = self.column_from;
+        let mut chars = self.source[from..offset].chars().peekable();
+        while let Some(char) = chars.next() {
+            column = match char {
+                '\n' => 1,
+                '\r' if chars.peek() != Some(&'\n') => 1,
+                '\r' => column,
+                '\t' => (column - 1) / self.tab_width * self.tab_width + self.tab_width + 1,
+                _ => column + 1,
+            };
+        }
+        self.column_from = (offset, column);
+        column
+    }
+
+    /// A Scan error located at the start of the token being scanned.
+    fn error(&self, kind: ErrorKind, message: String) -> CrustCoreErr {
+        CrustCoreErr::Scan {
+            kind,
+            line: self.line,
+            offset: self.start,
+            message,
+        }
     }
 
     fn is_at_end(&self) -> bool {
@@ -125,55 +339,279 @@ impl<'a> Scanner<'a> {
     }
 
     fn advance(&mut self) -> char {
-        self.current += 1;
-        self.char_at(self.current - 1)
+        let char = self.char_at(self.current);
+        self.current += char.len_utf8();
+        char
     }
 
     fn advance_if(&mut self, pattern: char) -> bool {
         if self.is_at_end() || self.char_at(self.current) != pattern {
             false
         } else {
-            self.current += 1;
+            self.current += pattern.len_utf8();
             true
         }
     }
 
     fn peek(&self) -> char {
-        if self.is_at_end() {
-            '\0'
-        } else {
-            self.char_at(self.current)
-        }
+        self.char_at(self.current)
     }
 
+    /// The character starting at byte `index`, which must lie on a character boundary. Most
+    /// source is ASCII, so single bytes are read directly and only the rest is decoded.
     fn char_at(&self, index: usize) -> char {
-        self.source[index..index + 1].chars().next().unwrap()
+        match self.source.as_bytes().get(index) {
+            Some(byte) if byte.is_ascii() => *byte as char,
+            Some(_) => self.source[index..].chars().next().unwrap_or('\0'),
+            None => '\0',
+        }
+    }
+
+    /// Whether the token being scanned is the first thing on its line.
+    fn at_line_start(&self) -> bool {
+        matches!(
+            self.source.as_bytes()[..self.start].last(),
+            None | Some(b'\n' | b'\r')
+        )
+    }
+
+    /// `#line <n> "<file>"`, for generated sources: the line after the directive is numbered `n`.
+    /// The file name is optional and is only checked, since positions do not carry a file.
+    fn take_line_directive(&mut self) -> CrustCoreResult {
+        self.skip_line();
+        let directive = &self.source[self.start..self.current];
+        let args = &directive["#line".len()..];
+        let mut parts = args.trim().splitn(2, char::is_whitespace);
+        // Lines are kept well below `usize::MAX`, so counting on from them cannot overflow.
+        let line = parts
+            .next()
+            .and_then(|n| u32::from_str(n).ok())
+            .map(|n| n as usize);
+        let file_ok = parts.next().is_none_or(|file| {
+            let file = file.trim();
+            file.len() >= 2 && file.starts_with('"') && file.ends_with('"')
+        });
+        match line {
+            Some(line) if line > 0 && file_ok && args.starts_with([' ', '\t']) => {
+                // The line break ending the directive moves on to line `n`.
+                self.line = line - 1;
+            }
+            _ => {
+                return Err(self.error(
+                    ErrorKind::InvalidLineDirective,
+                    format!("Invalid line directive '{directive}'"),
+                ))
+            }
+        }
+        Ok(())
     }
 
     fn take_string_literal(&mut self) -> CrustCoreResult {
+        // A string may span lines, so remember where it opened for the error below.
+        let start_line = self.line;
+        let mut text = Text::new(self.current);
+        let mut parts = vec![];
+        let mut invalid = None;
         while self.peek() != '\"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
+            let escape = self.current;
+            let mut char = self.advance();
+            let escaped = char == '\\' && !self.is_at_end();
+            if escaped {
+                char = self.advance();
+            }
+            self.count_line(char);
+            if !escaped && char == '$' && self.advance_if('{') {
+                if let Some(value) = text.take(self.source, escape) {
+                    parts.push(StringPart::Text(value));
+                }
+                match self.take_interpolation() {
+                    Ok(part) => parts.push(part),
+                    Err(message) => {
+                        invalid.get_or_insert(message);
+                    }
+                }
+                text = Text::new(self.current);
+                continue;
+            }
+            if !escaped {
+                text.push(char);
+                continue;
+            }
+            match self.decode_escape(char) {
+                Ok(decoded) => text.decode(self.source, escape, decoded),
+                Err(message) => {
+                    invalid.get_or_insert(message);
+                }
             }
-            self.advance();
         }
 
         if self.is_at_end() {
             return Err(CrustCoreErr::Scan {
-                line: self.line,
+                kind: ErrorKind::UnterminatedString,
+                line: start_line,
+                offset: self.start,
                 message: "Unterminated string literal".to_string(),
             });
         };
 
+        let end = self.current;
         self.advance();
 
-        self.push_token(Token::String(
-            self.source[self.start + 1..self.current - 1].to_string(),
-        ));
+        // The token's lexeme and length stay the raw source span; only the value is decoded.
+        if let Some(message) = invalid {
+            return Err(self.error(ErrorKind::InvalidLiteral, message));
+        }
+        if parts.is_empty() {
+            let value = text.take(self.source, end).unwrap_or_default();
+            self.push_token(Token::String(value));
+        } else {
+            if let Some(value) = text.take(self.source, end) {
+                parts.push(StringPart::Text(value));
+            }
+            self.push_token(Token::Interpolation(parts));
+        }
 
         Ok(())
     }
 
+    /// Takes the expression of a `${...}` in a string, whose `${` was just consumed, up to the
+    /// matching `}`. The expression is kept as source for the parser. It cannot contain string
+    /// literals of its own, so a `"` before the `}` means the `${` was never closed, and the
+    /// `"` ends the string.
+    fn take_interpolation(&mut self) -> Result<StringPart<'a>, String> {
+        let (offset, line) = (self.current, self.line);
+        let mut depth = 0;
+        while self.peek() != '\"' && !self.is_at_end() {
+            let char = self.advance();
+            self.count_line(char);
+            match char {
+                '{' => depth += 1,
+                '}' if depth == 0 => {
+                    let source = &self.source[offset..self.current - 1];
+                    if source.trim().is_empty() {
+                        return Err("Empty interpolation '${}' in string".to_string());
+                    }
+                    return Ok(StringPart::Expression {
+                        source: Cow::Borrowed(source),
+                        offset,
+                        line,
+                    });
+                }
+                '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        Err("Unterminated interpolation '${' in string".to_string())
+    }
+
+    /// Skips a comment after its `/*`, returning the text before the `*/`. Block comments may
+    /// span lines but do not nest, so the first `*/` ends one.
+    fn take_block_comment(&mut self) -> CrustCoreResult<&'a str> {
+        let start_line = self.line;
+        let text_start = self.current;
+        while !self.is_at_end() {
+            if self.source[self.current..].starts_with("*/") {
+                let text = &self.source[text_start..self.current];
+                self.current += 2;
+                return Ok(text);
+            }
+            let char = self.advance();
+            self.count_line(char);
+        }
+        Err(CrustCoreErr::Scan {
+            kind: ErrorKind::UnterminatedComment,
+            line: start_line,
+            offset: self.start,
+            message: "Unterminated block comment".to_string(),
+        })
+    }
+
+    /// Counts the line break `char` just consumed inside a literal, where `\r\n` counts once.
+    fn count_line(&mut self, char: char) {
+        if char == '\n' || (char == '\r' && self.peek() != '\n') {
+            self.line += 1;
+        }
+    }
+
+    fn take_char_literal(&mut self) -> CrustCoreResult {
+        if self.advance_if('\'') {
+            return Err(self.error(
+                ErrorKind::InvalidLiteral,
+                "Empty character literal".to_string(),
+            ));
+        }
+        if self.is_at_end() || matches!(self.peek(), '\n' | '\r') {
+            return Err(self.error(
+                ErrorKind::InvalidLiteral,
+                "Unterminated character literal".to_string(),
+            ));
+        }
+
+        let value = match self.advance() {
+            '\\' if !self.is_at_end() => {
+                let escaped = self.advance();
+                self.decode_escape(escaped)
+            }
+            value => Ok(value),
+        };
+
+        if !self.advance_if('\'') {
+            while !self.is_at_end() && !matches!(self.peek(), '\n' | '\r') {
+                if self.advance() == '\'' {
+                    return Err(self.error(
+                        ErrorKind::InvalidLiteral,
+                        "Character literal must contain exactly one character".to_string(),
+                    ));
+                }
+            }
+            return Err(self.error(
+                ErrorKind::InvalidLiteral,
+                "Unterminated character literal".to_string(),
+            ));
+        }
+
+        match value {
+            Ok(value) => {
+                self.push_token(Token::Char(value));
+                Ok(())
+            }
+            Err(message) => Err(self.error(ErrorKind::InvalidLiteral, message)),
+        }
+    }
+
+    /// The character for the escape whose backslash and `char` were just consumed, reading the
+    /// rest of a `\u{...}` escape, or the message for an invalid one.
+    fn decode_escape(&mut self, char: char) -> Result<char, String> {
+        if char != 'u' {
+            return escape(char).ok_or_else(|| format!("Invalid escape sequence '\\{char}'"));
+        }
+        if !self.advance_if('{') {
+            return Err("Unicode escape must be written as '\\u{...}'".to_string());
+        }
+        let mut digits = String::new();
+        while self.peek().is_ascii_hexdigit() {
+            digits.push(self.advance());
+        }
+        if !self.advance_if('}') {
+            return Err(format!("Unterminated unicode escape '\\u{{{digits}'"));
+        }
+        if digits.is_empty() {
+            return Err("Empty unicode escape '\\u{}'".to_string());
+        }
+        // At most six digits, so the value fits in a u32 and `\u{0000000041}` is rejected.
+        let code = u32::from_str_radix(&digits, 16)
+            .ok()
+            .filter(|_| digits.len() <= 6);
+        match code {
+            Some(0xD800..=0xDFFF) => Err(format!(
+                "Unicode escape '\\u{{{digits}}}' is a surrogate, not a character"
+            )),
+            Some(code) => char::from_u32(code)
+                .ok_or_else(|| format!("Unicode escape '\\u{{{digits}}}' is out of range")),
+            None => Err(format!("Unicode escape '\\u{{{digits}}}' is out of range")),
+        }
+    }
+
     fn take_number_literal(&mut self) -> CrustCoreResult {
         while self.peek().is_ascii_digit() {
             self.advance();
@@ -187,53 +625,119 @@ impl<'a> Scanner<'a> {
         }
 
         let literal = &self.source[self.start..self.current];
-        if literal.contains('.') {
-            if let Ok(val) = f32::from_str(literal) {
+        let fractional = literal.contains('.');
+        // A trailing `i` or `f` forces the type, e.g. `5f` is a Float, unless it begins a word.
+        let float = match self.peek() {
+            suffix @ ('i' | 'f') if !is_word_char(self.peek_next()) => {
+                self.advance();
+                if suffix == 'i' && fractional {
+                    return Err(self.error(
+                        ErrorKind::InvalidLiteral,
+                        format!("Fractional literal '{literal}' cannot be an Integer"),
+                    ));
+                }
+                suffix == 'f'
+            }
+            _ => fractional,
+        };
+        if float {
+            if let Ok(val) = f64::from_str(literal) {
                 self.push_token(Token::Float(val));
             } else {
-                return Err(CrustCoreErr::Scan {
-                    line: self.line,
-                    message: "Invalid float value".to_string(),
-                });
+                return Err(
+                    self.error(ErrorKind::InvalidLiteral, "Invalid float value".to_string())
+                );
             }
-        } else if let Ok(val) = i32::from_str(literal) {
-            self.push_token(Token::Integer(val));
         } else {
-            return Err(CrustCoreErr::Scan {
-                line: self.line,
-                message: "Invalid integer value".to_string(),
-            });
+            // The literal is only digits, so the one way it can fail is by being too large.
+            match i64::from_str(literal) {
+                Ok(val) => self.push_token(Token::Integer(val)),
+                Err(err) if *err.kind() == IntErrorKind::PosOverflow => {
+                    return Err(self.error(
+                        ErrorKind::InvalidLiteral,
+                        format!(
+                            "Integer literal '{literal}' is too large for an i64, \
+                         write '{literal}f' to make it a Float"
+                        ),
+                    ));
+                }
+                Err(_) => {
+                    return Err(self.error(
+                        ErrorKind::InvalidLiteral,
+                        "Invalid integer value".to_string(),
+                    ))
+                }
+            }
         }
 
         Ok(())
     }
 
     fn peek_next(&self) -> char {
-        if self.current + 1 >= self.source.len() {
-            '\0'
-        } else {
-            self.char_at(self.current + 1)
+        match self.source.as_bytes().get(self.current) {
+            Some(byte) if byte.is_ascii() => self.char_at(self.current + 1),
+            Some(_) => self.source[self.current..].chars().nth(1).unwrap_or('\0'),
+            None => '\0',
         }
     }
 
     fn take_identifier(&mut self) -> CrustCoreResult<()> {
-        while self.peek().is_alphanumeric() || self.peek() == '_' {
-            self.advance();
+        while let Some(byte) = self.source.as_bytes().get(self.current) {
+            if byte.is_ascii_alphanumeric() || *byte == b'_' {
+                self.current += 1;
+            } else if !byte.is_ascii() && self.peek().is_alphanumeric() {
+                self.advance();
+            } else {
+                break;
+            }
         }
         let text = &self.source[self.start..self.current];
 
         if let Some(keyword) = try_as_keyword(text) {
             self.push_token(keyword);
         } else {
-            self.push_token(Token::Identifier(text.to_string()));
+            self.push_token(Token::Identifier(Symbol::intern(text)));
         }
         Ok(())
     }
 }
 
+fn is_word_char(char: char) -> bool {
+    char.is_alphanumeric() || char == '_'
+}
+
+/// The character a backslash escape such as `\n` stands for.
+fn escape(char: char) -> Option<char> {
+    match char {
+        'n' => Some('\n'),
+        't' => Some('\t'),
+        'r' => Some('\r'),
+        '0' => Some('\0'),
+        '\\' | '\'' | '\"' | '$' => Some(char),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parser::Parser;
+
+    #[test]
+    fn scan_empty_source() {
+        let tokens = Scanner::new("").scan_tokens().unwrap();
+        assert_eq!(
+            tokens,
+            [SourceToken {
+                column: 1,
+                ..SourceToken::new(Token::Eof, "", 0, 1)
+            }]
+        );
+        assert_eq!(
+            (tokens[0].offset, tokens[0].line, tokens[0].length),
+            (0, 1, 0)
+        );
+    }
 
     #[test]
     fn scan_basic_symbols() {
@@ -248,8 +752,11 @@ mod tests {
             Token::Plus,
             Token::Semicolon,
             Token::Star,
+            Token::Percent,
+            Token::StarStar,
+            Token::Star,
         ];
-        let scanner = Scanner::new("(){},.-+;*");
+        let scanner = Scanner::new("(){},.-+;*%***");
         let tokens = scanner.scan_tokens();
 
         tokens
@@ -271,8 +778,11 @@ mod tests {
             Token::Less,
             Token::GreaterEqual,
             Token::Greater,
+            Token::QuestionQuestion,
+            Token::Question,
+            Token::Colon,
         ];
-        let scanner = Scanner::new("==!==!<=<>=>");
+        let scanner = Scanner::new("==!==!<=<>=>???:");
         let tokens = scanner.scan_tokens();
 
         tokens
@@ -283,6 +793,178 @@ mod tests {
             .for_each(|(token, symbol)| assert_eq!(*token, symbol))
     }
 
+    #[test]
+    fn scan_colons() {
+        let tokens = Scanner::new("a: b::c :::")
+            .scan_tokens()
+            .unwrap()
+            .into_iter()
+            .map(|st| st.token)
+            .collect::<Vec<Token>>();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Identifier("a".into()),
+                Token::Colon,
+                Token::Identifier("b".into()),
+                Token::ColonColon,
+                Token::Identifier("c".into()),
+                Token::ColonColon,
+                Token::Colon,
+                Token::Eof
+            ]
+        );
+    }
+
+    #[test]
+    fn borrowed_tokens_match_owned_ones() {
+        let source = "let näme = \"a\\tb\";";
+        let (borrowed, errors) = Scanner::new(source).scan_borrowed();
+        assert!(errors.is_empty());
+        assert_eq!(borrowed[1].lexeme, "näme");
+        assert_eq!(borrowed[3].lexeme, "\"a\\tb\"");
+        assert_eq!(borrowed[3].token, Token::String("a\tb".into()));
+        // The lexemes point into the source rather than at copies.
+        assert!(std::ptr::eq(borrowed[1].lexeme, &source[4..9]));
+
+        let owned = borrowed.into_iter().map(BorrowedToken::into_owned);
+        assert!(owned.eq(Scanner::new(source).scan_tokens().unwrap()));
+    }
+
+    #[test]
+    fn borrowed_payloads_only_copy_decoded_text() {
+        let source = "\"plain\" \"a\\tb\" \"n=${n}!\" // note\n/* block */";
+        let (borrowed, errors) = Scanner::new(source).keep_comments(true).scan_borrowed();
+        assert!(errors.is_empty());
+        let borrows = |text: &Cow<str>, at: &str| match text {
+            Cow::Borrowed(text) => std::ptr::eq(*text, at),
+            Cow::Owned(_) => false,
+        };
+        match &borrowed[..] {
+            [BorrowedToken {
+                token: Token::String(plain),
+                ..
+            }, BorrowedToken {
+                token: Token::String(escaped),
+                ..
+            }, BorrowedToken {
+                token: Token::Interpolation(parts),
+                ..
+            }, BorrowedToken {
+                token: Token::LineComment(line),
+                ..
+            }, BorrowedToken {
+                token: Token::BlockComment(block),
+                ..
+            }, _] => {
+                assert!(borrows(plain, &source[1..6]));
+                assert!(matches!(escaped, Cow::Owned(text) if text == "a\tb"));
+                match &parts[..] {
+                    [StringPart::Text(text), StringPart::Expression { source: n, .. }, StringPart::Text(bang)] =>
+                    {
+                        assert!(borrows(text, &source[16..18]));
+                        assert!(borrows(n, &source[20..21]));
+                        assert!(borrows(bang, &source[22..23]));
+                    }
+                    other => panic!("Expected three parts, got {other:?}"),
+                }
+                assert!(borrows(line, &source[27..32]));
+                assert!(borrows(block, &source[35..42]));
+            }
+            other => panic!("Expected five tokens and Eof, got {other:?}"),
+        }
+
+        // Owned tokens outlive the source.
+        let owned: Vec<Token<'static>> = {
+            let source = source.to_string();
+            let (tokens, _) = Scanner::new(&source).keep_comments(true).scan_borrowed();
+            tokens
+                .into_iter()
+                .map(|token| token.token.into_owned())
+                .collect()
+        };
+        assert_eq!(owned[0], Token::String("plain".into()));
+        assert_eq!(owned[4], Token::BlockComment(" block ".into()));
+    }
+
+    #[test]
+    fn scan_annotation_symbols() {
+        let tokens = Scanner::new("a: Int) -> - >")
+            .scan_tokens()
+            .unwrap()
+            .into_iter()
+            .map(|st| st.token)
+            .collect::<Vec<Token>>();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Identifier("a".into()),
+                Token::Colon,
+                Token::Identifier("Int".into()),
+                Token::RightParen,
+                Token::Arrow,
+                Token::Minus,
+                Token::Greater,
+                Token::Eof
+            ]
+        );
+    }
+
+    #[test]
+    fn scan_line_endings() {
+        let lines = |source| {
+            Scanner::new(source)
+                .scan_tokens()
+                .unwrap()
+                .into_iter()
+                .map(|st| st.line)
+                .collect::<Vec<usize>>()
+        };
+        assert_eq!(lines("a\r\nb\r\n\r\nc"), vec![1, 2, 4, 4]);
+        assert_eq!(lines("a\rb\r\rc"), vec![1, 2, 4, 4]);
+        assert_eq!(lines("a // comment\rb \"x\ry\" c"), vec![1, 2, 3, 3, 3]);
+    }
+
+    #[test]
+    fn scan_ranges() {
+        let tokens = |source| {
+            Scanner::new(source)
+                .scan_tokens()
+                .unwrap()
+                .into_iter()
+                .map(|st| st.token)
+                .collect::<Vec<Token>>()
+        };
+        assert_eq!(
+            tokens("1..5"),
+            vec![
+                Token::Integer(1),
+                Token::DotDot,
+                Token::Integer(5),
+                Token::Eof
+            ]
+        );
+        assert_eq!(
+            tokens("0..=9"),
+            vec![
+                Token::Integer(0),
+                Token::DotDotEqual,
+                Token::Integer(9),
+                Token::Eof
+            ]
+        );
+        assert_eq!(
+            tokens("1.5..2."),
+            vec![
+                Token::Float(1.5),
+                Token::DotDot,
+                Token::Integer(2),
+                Token::Dot,
+                Token::Eof
+            ]
+        );
+    }
+
     #[test]
     fn scan_whitespace() {
         let symbols = vec![
@@ -322,6 +1004,62 @@ mod tests {
             .for_each(|(token, symbol)| assert_eq!(*token, symbol))
     }
 
+    #[test]
+    fn comments_are_kept_on_request() {
+        let source = "a // hi\n/* x\ny */ b /**/";
+        let kinds = |scanner: Scanner| {
+            scanner
+                .scan_tokens()
+                .unwrap()
+                .into_iter()
+                .map(|st| (st.token, st.line))
+                .collect::<Vec<_>>()
+        };
+        let a = (Token::Identifier("a".into()), 1);
+        let b = (Token::Identifier("b".into()), 3);
+        assert_eq!(
+            kinds(Scanner::new(source)),
+            vec![a.clone(), b.clone(), (Token::Eof, 3)]
+        );
+        assert_eq!(
+            kinds(Scanner::new(source).keep_comments(true)),
+            vec![
+                a,
+                (Token::LineComment(" hi".into()), 1),
+                (Token::BlockComment(" x\ny ".into()), 3),
+                b,
+                (Token::BlockComment("".into()), 3),
+                (Token::Eof, 3)
+            ]
+        );
+
+        let tokens = Scanner::new("print 1; // one\n/* two */ print 2;")
+            .keep_comments(true)
+            .scan_tokens()
+            .unwrap();
+        assert_eq!(tokens[3].lexeme, "// one");
+        assert_eq!(tokens[4].lexeme, "/* two */");
+        assert_eq!(Parser::new(tokens).parse_program().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn unterminated_block_comment() {
+        match Scanner::new("a /* b\n c").scan_tokens() {
+            Err(CrustCoreErr::Multi { errors }) => match &errors[..] {
+                [CrustCoreErr::Scan {
+                    kind: ErrorKind::UnterminatedComment,
+                    line: 1,
+                    offset: 2,
+                    message,
+                }] => assert_eq!(message, "Unterminated block comment"),
+                other => panic!("Expected one Scan error, got {other:?}"),
+            },
+            other => panic!("Expected a Scan error, got {other:?}"),
+        }
+        assert!(Scanner::new("/* still typing").needs_more_input());
+        assert!(!Scanner::new("/* done */").needs_more_input());
+    }
+
     #[test]
     fn scan_float_literal_with_access() {
         let symbols = vec![
@@ -362,12 +1100,221 @@ mod tests {
             .for_each(|(token, symbol)| assert_eq!(*token, symbol))
     }
 
+    #[test]
+    fn scan_non_ascii_identifiers() {
+        let tokens = Scanner::new("let café_1 = näive; // ünïcode")
+            .scan_tokens()
+            .unwrap();
+        let identifiers = tokens
+            .iter()
+            .filter_map(|st| match &st.token {
+                Token::Identifier(name) => Some(name.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(identifiers, vec!["café_1", "näive"]);
+        assert_eq!(tokens.last().unwrap().token, Token::Eof);
+    }
+
+    #[test]
+    fn scan_increments() {
+        let tokens = Scanner::new("i++ j-- +-").scan_tokens().unwrap();
+        let kinds = tokens.iter().map(|st| &st.token).collect::<Vec<_>>();
+        assert_eq!(
+            kinds[1..],
+            [
+                &Token::PlusPlus,
+                &Token::Identifier("j".into()),
+                &Token::MinusMinus,
+                &Token::Plus,
+                &Token::Minus,
+                &Token::Eof
+            ]
+        );
+    }
+
+    #[test]
+    fn scan_logical_and_bitwise_operators() {
+        let tokens = Scanner::new("& && | ||").scan_tokens().unwrap();
+        let kinds = tokens.iter().map(|st| &st.token).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                &Token::BitAnd,
+                &Token::And,
+                &Token::BitOr,
+                &Token::Or,
+                &Token::Eof
+            ]
+        );
+        assert_eq!(tokens[1].lexeme, "&&");
+    }
+
+    #[test]
+    fn multi_char_operators_take_the_longest_match() {
+        let tokens = Scanner::new("!=< <== &&& ..= ??").scan_tokens().unwrap();
+        let kinds = tokens.iter().map(|st| &st.token).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                &Token::BangEqual,
+                &Token::Less,
+                &Token::LessEqual,
+                &Token::Equal,
+                &Token::And,
+                &Token::BitAnd,
+                &Token::DotDotEqual,
+                &Token::QuestionQuestion,
+                &Token::Eof
+            ]
+        );
+    }
+
+    #[test]
+    fn line_directive_renumbers_following_lines() {
+        let tokens = Scanner::new("a\n#line 100 \"template.crust\"\nb\nc")
+            .scan_tokens()
+            .unwrap();
+        let lines = tokens.iter().map(|st| st.line).collect::<Vec<_>>();
+        assert_eq!(lines, [1, 100, 101, 101]);
+        assert_eq!(tokens[1].token, Token::Identifier("b".into()));
+
+        let tokens = Scanner::new("#line 7\nx").scan_tokens().unwrap();
+        assert_eq!(tokens[0].line, 7);
+
+        assert!(Scanner::new("#line x\n").scan_tokens().is_err());
+        assert!(Scanner::new("#line 0\n").scan_tokens().is_err());
+        assert!(Scanner::new("#line1\n").scan_tokens().is_err());
+        assert!(Scanner::new("a #line 3\n").scan_tokens().is_err());
+        assert!(Scanner::new("#define x\n").scan_tokens().is_err());
+    }
+
+    #[test]
+    fn tabs_advance_to_the_next_tab_stop() {
+        let columns = |source, tab_width| {
+            Scanner::new(source)
+                .with_tab_width(tab_width)
+                .scan_tokens()
+                .unwrap()
+                .iter()
+                .map(|st| (st.column, st.offset))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(columns("\tx", 1), [(2, 1), (3, 2)]);
+        assert_eq!(columns("\tx", 4), [(5, 1), (6, 2)]);
+        assert_eq!(columns("ab\tx\n\ty", 4), [(1, 0), (5, 3), (5, 6), (6, 7)]);
+        assert_eq!(columns("\"\t\" x", 8), [(1, 0), (11, 4), (12, 5)]);
+    }
+
+    #[test]
+    fn number_suffixes_force_the_type() {
+        let token = |source| Scanner::new(source).scan_tokens().unwrap()[0].token.clone();
+        assert_eq!(token("5i"), Token::Integer(5));
+        assert_eq!(token("5f"), Token::Float(5.0));
+        assert_eq!(token("5.5f"), Token::Float(5.5));
+        assert_eq!(Scanner::new("5f;").scan_tokens().unwrap()[0].lexeme, "5f");
+        assert_eq!(token("5in"), Token::Integer(5));
+        match Scanner::new("5.5i").scan_tokens() {
+            Err(CrustCoreErr::Multi { errors }) => assert!(matches!(
+                &errors[..],
+                [CrustCoreErr::Scan { message, .. }]
+                    if message == "Fractional literal '5.5' cannot be an Integer"
+            )),
+            other => panic!("Expected a Scan error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn scan_newlines_when_asked() {
+        let tokens = Scanner::new("a\r\nb // c\r\"d\ne\"")
+            .emit_newlines(true)
+            .scan_tokens()
+            .unwrap();
+        let kinds = tokens
+            .iter()
+            .map(|st| (&st.token, st.line))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                (&Token::Identifier("a".into()), 1),
+                (&Token::Newline, 1),
+                (&Token::Identifier("b".into()), 2),
+                (&Token::Newline, 2),
+                (&Token::String("d\ne".into()), 4),
+                (&Token::Eof, 4)
+            ]
+        );
+    }
+
+    #[test]
+    fn punctuation_between_the_letters_is_not_an_identifier() {
+        for source in ["\\", "^", "`"] {
+            assert!(Scanner::new(source).scan_tokens().is_err(), "{source}");
+        }
+        let tokens = Scanner::new("_a Zz").scan_tokens().unwrap();
+        assert_eq!(tokens[0].token, Token::Identifier("_a".into()));
+        assert_eq!(tokens[1].token, Token::Identifier("Zz".into()));
+    }
+
+    #[test]
+    fn huge_line_directive_does_not_overflow() {
+        let source = format!("#line {}\n\nx", usize::MAX);
+        assert!(Scanner::new(&source).scan_tokens().is_err());
+        let tokens = Scanner::new("#line 4294967295\n\nx").scan_tokens().unwrap();
+        assert_eq!(tokens[0].line, 4294967296);
+    }
+
+    #[test]
+    fn scan_skips_shebang() {
+        let tokens = Scanner::new("#!/usr/bin/env crust\nprint 1;")
+            .scan_tokens()
+            .unwrap();
+        assert_eq!(tokens[0].token, Token::Print);
+        assert_eq!((tokens[0].line, tokens[0].offset), (2, 21));
+        assert!(Scanner::new("print 1;\n#!/usr/bin/env crust")
+            .scan_tokens()
+            .is_err());
+        assert!(Scanner::new(" #!x").scan_tokens().is_err());
+    }
+
+    #[test]
+    fn scan_wide_numbers() {
+        let tokens = Scanner::new("3000000000 9999999999 0.1 9223372036854775808f")
+            .scan_tokens()
+            .unwrap();
+        assert_eq!(tokens[0].token, Token::Integer(3_000_000_000));
+        assert_eq!(tokens[1].token, Token::Integer(9_999_999_999));
+        assert_eq!(tokens[2].token, Token::Float(0.1));
+        assert_eq!(tokens[3].token, Token::Float(9_223_372_036_854_775_808.0));
+        match Scanner::new("9223372036854775808").scan_tokens() {
+            Err(CrustCoreErr::Multi { errors }) => match &errors[..] {
+                [CrustCoreErr::Scan { message, .. }] => assert_eq!(
+                    message,
+                    "Integer literal '9223372036854775808' is too large for an i64, \
+                     write '9223372036854775808f' to make it a Float"
+                ),
+                other => panic!("Expected one Scan error, got {other:?}"),
+            },
+            other => panic!("Expected a Scan error, got {other:?}"),
+        }
+    }
+
     #[test]
     fn scan_string_literal() {
+        let at_column = |token, column| SourceToken { column, ..token };
         let symbols = vec![
-            SourceToken::new(Token::LeftParen, 0, 1, 1),
-            SourceToken::new(Token::String("This is a string".to_string()), 1, 1, 18),
-            SourceToken::new(Token::RightParen, 19, 1, 1),
+            at_column(SourceToken::new(Token::LeftParen, "(", 0, 1), 1),
+            at_column(
+                SourceToken::new(
+                    Token::String("This is a string".into()),
+                    "\"This is a string\"",
+                    1,
+                    1,
+                ),
+                2,
+            ),
+            at_column(SourceToken::new(Token::RightParen, ")", 19, 1), 20),
         ];
         let scanner = Scanner::new("(\"This is a string\")");
         let tokens = scanner.scan_tokens();
@@ -379,6 +1326,199 @@ mod tests {
             .for_each(|(token, symbol)| assert_eq!(*token, symbol))
     }
 
+    #[test]
+    fn string_escapes_keep_raw_span() {
+        let tokens = Scanner::new(r#""\n" "a\"b" x"#).scan_tokens().unwrap();
+        assert_eq!(tokens[0].token, Token::String("\n".into()));
+        assert_eq!((tokens[0].offset, tokens[0].length), (0, 4));
+        assert_eq!(tokens[0].lexeme, r#""\n""#);
+        assert_eq!(tokens[1].token, Token::String("a\"b".into()));
+        assert_eq!((tokens[1].offset, tokens[1].length), (5, 6));
+        assert_eq!(tokens[2].token, Token::Identifier("x".into()));
+        assert_eq!(tokens[2].offset, 12);
+
+        let (tokens, errors) = Scanner::new(r#""a\qb" x"#).scan();
+        assert!(matches!(
+            &errors[..],
+            [CrustCoreErr::Scan { message, .. }] if message == "Invalid escape sequence '\\q'"
+        ));
+        assert_eq!(tokens[0].token, Token::Identifier("x".into()));
+    }
+
+    #[test]
+    fn unicode_escapes_name_valid_chars() {
+        let tokens = Scanner::new(r#""\u{1F600}!" '\u{e9}'"#)
+            .scan_tokens()
+            .unwrap();
+        assert_eq!(tokens[0].token, Token::String("\u{1F600}!".into()));
+        assert_eq!(tokens[1].token, Token::Char('é'));
+
+        let message = |source| {
+            let (_, errors) = Scanner::new(source).scan();
+            match &errors[..] {
+                [CrustCoreErr::Scan { message, .. }] => message.clone(),
+                other => panic!("Expected a single Scan error for {source}, got {other:?}"),
+            }
+        };
+        assert_eq!(message(r#""\u{}""#), "Empty unicode escape '\\u{}'");
+        assert_eq!(
+            message(r#""\u{D800}""#),
+            "Unicode escape '\\u{D800}' is a surrogate, not a character"
+        );
+        assert_eq!(
+            message(r#""\u{110000}""#),
+            "Unicode escape '\\u{110000}' is out of range"
+        );
+        assert_eq!(
+            message(r#""\u{0000041}""#),
+            "Unicode escape '\\u{0000041}' is out of range"
+        );
+        assert_eq!(
+            message(r#""\u41""#),
+            "Unicode escape must be written as '\\u{...}'"
+        );
+        assert_eq!(
+            message(r#""\u{41""#),
+            "Unterminated unicode escape '\\u{41'"
+        );
+        assert_eq!(message(r#"'\u{DFFF}'"#), message(r#""\u{DFFF}""#));
+    }
+
+    #[test]
+    fn interpolations_split_strings() {
+        let tokens = Scanner::new("\"n=${n + 1}!\" \"${a}\" \"\\${a}\"")
+            .scan_tokens()
+            .unwrap();
+        assert_eq!(
+            tokens[0].token,
+            Token::Interpolation(vec![
+                StringPart::Text("n=".into()),
+                StringPart::Expression {
+                    source: "n + 1".into(),
+                    offset: 5,
+                    line: 1,
+                },
+                StringPart::Text("!".into()),
+            ])
+        );
+        assert_eq!(tokens[0].lexeme, "\"n=${n + 1}!\"");
+        assert_eq!(
+            tokens[1].token,
+            Token::Interpolation(vec![StringPart::Expression {
+                source: "a".into(),
+                offset: 17,
+                line: 1,
+            }])
+        );
+        assert_eq!(tokens[2].token, Token::String("${a}".into()));
+
+        let (tokens, errors) = Scanner::new("\"a ${b\" x").scan();
+        assert!(matches!(
+            &errors[..],
+            [CrustCoreErr::Scan { message, .. }]
+                if message == "Unterminated interpolation '${' in string"
+        ));
+        assert_eq!(tokens[0].token, Token::Identifier("x".into()));
+    }
+
+    #[test]
+    fn scan_char_literals() {
+        let tokens = Scanner::new("'a' '\\n' '\\'' 'é'").scan_tokens().unwrap();
+        let chars = tokens.iter().map(|st| &st.token).collect::<Vec<&Token>>();
+        assert_eq!(
+            chars,
+            vec![
+                &Token::Char('a'),
+                &Token::Char('\n'),
+                &Token::Char('\''),
+                &Token::Char('é'),
+                &Token::Eof
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_char_literals() {
+        let message = |source| {
+            let (_, errors) = Scanner::new(source).scan();
+            match &errors[..] {
+                [CrustCoreErr::Scan { message, .. }] => message.clone(),
+                other => panic!("Expected a single Scan error for {source}, got {other:?}"),
+            }
+        };
+        assert_eq!(message("''"), "Empty character literal");
+        assert_eq!(
+            message("'ab'"),
+            "Character literal must contain exactly one character"
+        );
+        assert_eq!(message("'a"), "Unterminated character literal");
+        assert_eq!(message("'\\q'"), "Invalid escape sequence '\\q'");
+    }
+
+    #[test]
+    fn scan_lexemes() {
+        let tokens = Scanner::new("let x = 1.50;").scan_tokens().unwrap();
+        let lexemes = tokens
+            .iter()
+            .map(|st| st.lexeme.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(lexemes, vec!["let", "x", "=", "1.50", ";", ""]);
+        assert_eq!(tokens[3].token, Token::Float(1.5));
+        assert_eq!(tokens[3].length, 4);
+    }
+
+    #[test]
+    fn scan_unexpected_character() {
+        match Scanner::new("let a = 1;\nprint a @ 2;").scan_tokens() {
+            Err(CrustCoreErr::Multi { errors }) => match &errors[..] {
+                [CrustCoreErr::Scan {
+                    kind: ErrorKind::UnexpectedCharacter,
+                    line,
+                    offset,
+                    message,
+                }] => {
+                    assert_eq!(*line, 2);
+                    assert_eq!(*offset, 19);
+                    assert_eq!(message, "Unexpected character '@'");
+                }
+                other => panic!("Expected a single Scan error, got {other:?}"),
+            },
+            other => panic!("Expected a Scan error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn scan_keeps_tokens_around_errors() {
+        let (tokens, errors) = Scanner::new("1 @ 2").scan();
+        let tokens = tokens
+            .into_iter()
+            .map(|st| st.token)
+            .collect::<Vec<Token>>();
+        assert_eq!(
+            tokens,
+            vec![Token::Integer(1), Token::Integer(2), Token::Eof]
+        );
+        assert!(matches!(
+            &errors[..],
+            [CrustCoreErr::Scan { offset: 2, .. }]
+        ));
+    }
+
+    #[test]
+    fn unterminated_string_points_at_opening_quote() {
+        match Scanner::new("print 1;\nprint \"one\ntwo\nthree").scan_tokens() {
+            Err(CrustCoreErr::Multi { errors }) => assert!(matches!(
+                &errors[..],
+                [CrustCoreErr::Scan {
+                    line: 2,
+                    offset: 15,
+                    ..
+                }]
+            )),
+            other => panic!("Expected a Scan error, got {other:?}"),
+        }
+    }
+
     #[test]
     fn scan_identifiers() {
         let symbols = vec![
@@ -388,7 +1528,7 @@ mod tests {
             Token::Class,
             Token::Super,
             Token::Fn,
-            Token::Identifier("some_name_1".to_string()),
+            Token::Identifier("some_name_1".into()),
             Token::True,
             Token::False,
             Token::Mut,
@@ -398,8 +1538,12 @@ mod tests {
             Token::Return,
             Token::This,
             Token::Let,
+            Token::Print,
+            Token::Nil,
+            Token::Const,
+            Token::In,
         ];
-        let scanner = Scanner::new("if else for class super fn some_name_1 true false mut while loop break return this let");
+        let scanner = Scanner::new("if else for class super fn some_name_1 true false mut while loop break return this let print nil const in");
         let tokens = scanner.scan_tokens();
 
         tokens
@@ -409,4 +1553,15 @@ mod tests {
             .zip(symbols)
             .for_each(|(token, symbol)| assert_eq!(*token, symbol))
     }
+
+    #[test]
+    fn incomplete_input() {
+        assert!(Scanner::new("fn f() {").needs_more_input());
+        assert!(Scanner::new("print (1 +").needs_more_input());
+        assert!(Scanner::new("print \"unterminated").needs_more_input());
+        assert!(!Scanner::new("fn f() {}").needs_more_input());
+        assert!(!Scanner::new("print \"(\";").needs_more_input());
+        assert!(!Scanner::new("print 1; }").needs_more_input());
+        assert!(!Scanner::new("print \"\\q\";").needs_more_input());
+    }
 }
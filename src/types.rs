@@ -0,0 +1,162 @@
+use std::fmt;
+
+use crust_grammar::token::{SourceToken, Token};
+
+use crate::{
+    ast::{Expression, Statement},
+    util::{CrustCoreErr, CrustCoreResult, ErrorKind},
+};
+
+/// The types that annotations such as `let x: Int` can name.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Type {
+    Int,
+    Float,
+    Str,
+    Bool,
+    Nil,
+}
+
+impl Type {
+    /// The type an annotation names, or `None` for names the checker does not know.
+    pub fn from_name(name: &str) -> Option<Type> {
+        match name {
+            "Int" => Some(Type::Int),
+            "Float" => Some(Type::Float),
+            "Str" => Some(Type::Str),
+            "Bool" => Some(Type::Bool),
+            "Nil" => Some(Type::Nil),
+            _ => None,
+        }
+    }
+
+    /// The type of a literal value.
+    fn of_literal(value: &SourceToken) -> Option<Type> {
+        match value.token {
+            Token::Integer(_) => Some(Type::Int),
+            Token::Float(_) => Some(Type::Float),
+            Token::String(_) | Token::Char(_) => Some(Type::Str),
+            Token::True | Token::False => Some(Type::Bool),
+            Token::Nil => Some(Type::Nil),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Checks annotated `let`s whose initializer is a literal, and `return`s of literals from
+/// functions with a declared return type, against the annotated type. Anything else, including
+/// unannotated code and names other than the `Type`s, is left to run unchecked.
+pub fn check(program: &[Statement]) -> CrustCoreResult {
+    let mut errors = vec![];
+    check_block(program, None, &mut errors);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(CrustCoreErr::Multi { errors })
+    }
+}
+
+fn check_block(statements: &[Statement], returns: Option<Type>, errors: &mut Vec<CrustCoreErr>) {
+    for statement in statements {
+        match statement {
+            Statement::Block { statements, .. } => check_block(statements, returns, errors),
+            Statement::For { body, .. } => check_block(body, returns, errors),
+            Statement::Function {
+                return_type, body, ..
+            } => {
+                let returns = return_type.as_deref().and_then(Type::from_name);
+                check_block(body, returns, errors);
+            }
+            Statement::Let {
+                name,
+                annotation: Some(annotation),
+                initializer: Some(initializer),
+                ..
+            } => {
+                if let Some(expected) = Type::from_name(annotation) {
+                    errors.extend(mismatch(expected, initializer, || {
+                        format!("'{name}' is declared {expected}")
+                    }));
+                }
+            }
+            Statement::Return { value, line } => {
+                let Some(expected) = returns else {
+                    continue;
+                };
+                let context = || format!("Function must return {expected}");
+                match value {
+                    Some(value) => errors.extend(mismatch(expected, value, context)),
+                    None if expected != Type::Nil => errors.push(CrustCoreErr::Parse {
+                        kind: ErrorKind::TypeMismatch,
+                        line: *line,
+                        message: format!("{}, found Nil", context()),
+                        span: None,
+                    }),
+                    None => {}
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The error for `expr` when it is a literal of a type other than `expected`.
+fn mismatch(
+    expected: Type,
+    expr: &Expression,
+    context: impl Fn() -> String,
+) -> Option<CrustCoreErr> {
+    let Expression::Literal { value } = expr else {
+        return None;
+    };
+    let found = Type::of_literal(value)?;
+    (found != expected).then(|| CrustCoreErr::Parse {
+        kind: ErrorKind::TypeMismatch,
+        line: value.line,
+        message: format!("{}, found {found}", context()),
+        span: Some(value.offset..value.offset + value.length),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, scanner::Scanner};
+
+    fn check_source(source: &str) -> CrustCoreResult {
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+        check(&Parser::new(tokens).parse_program().unwrap())
+    }
+
+    #[test]
+    fn matching_annotations_pass() {
+        assert!(check_source("let x: Int = 1; let mut s: Str = \"s\"; let y = true;").is_ok());
+        assert!(check_source("fn f() -> Float { return 1.5; }").is_ok());
+        assert!(check_source("let x: Int = y; let z: Point = 1;").is_ok());
+        assert!(check_source("let c: Str = 'c';").is_ok());
+        assert!(check_source("let c: Int = 'c';").is_err());
+    }
+
+    #[test]
+    fn mismatching_annotations_are_errors() {
+        match check_source("let x: Int = \"s\";\nfn f() -> Bool {\n  return nil;\n}") {
+            Err(CrustCoreErr::Multi { errors }) => {
+                let messages = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+                assert_eq!(
+                    messages,
+                    [
+                        "[line 1] Error: 'x' is declared Int, found Str",
+                        "[line 3] Error: Function must return Bool, found Nil",
+                    ]
+                );
+            }
+            other => panic!("Expected type errors, got {other:?}"),
+        }
+    }
+}
@@ -1,8 +1,571 @@
+use std::{
+    cell::RefCell,
+    fmt, io,
+    ops::Range,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
 #[derive(Debug)]
 pub enum CrustCoreErr {
-    Multi { errors: Vec<CrustCoreErr> },
-    Scan { line: usize, message: String },
-    Runtime,
+    Multi {
+        errors: Vec<CrustCoreErr>,
+    },
+    Scan {
+        kind: ErrorKind,
+        line: usize,
+        offset: usize,
+        message: String,
+    },
+    /// `span` is the byte range of the offending source, when the parser knows it.
+    Parse {
+        kind: ErrorKind,
+        line: usize,
+        message: String,
+        span: Option<Range<usize>>,
+    },
+    /// `span` is the byte range of the operator that failed, for errors raised by one.
+    Runtime {
+        kind: ErrorKind,
+        line: usize,
+        message: String,
+        span: Option<Range<usize>>,
+    },
+    /// An error in the file at `path`, when several files are handled at once.
+    File {
+        path: PathBuf,
+        error: Box<CrustCoreErr>,
+    },
+    /// A file could not be read.
+    Io {
+        message: String,
+    },
+}
+
+/// What went wrong, given to an error where it is raised. The kind decides the error's
+/// [`code`](CrustCoreErr::code), so that rewording a message never changes its code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    // Scan errors.
+    UnexpectedCharacter,
+    UnterminatedString,
+    InvalidLineDirective,
+    UnterminatedComment,
+    /// A malformed number, character or escape, or a bad interpolation in a string.
+    InvalidLiteral,
+
+    // Parse errors.
+    /// A missing token or expression.
+    Expected,
+    NestingTooDeep,
+    /// An assignment or increment of something other than a variable.
+    InvalidTarget,
+    TopLevelReturn,
+    /// A value or return type that does not match its annotation.
+    TypeMismatch,
+    DuplicateParameter,
+
+    // Runtime errors.
+    UndefinedVariable,
+    DivisionByZero,
+    IntegerOverflow,
+    WrongArgumentCount,
+    /// An assignment to an immutable variable or a constant.
+    ImmutableAssignment,
+    CallsTooDeep,
+    /// Any other operation that cannot be carried out, e.g. adding a Str to an Integer.
+    InvalidOperation,
+}
+
+impl ErrorKind {
+    /// The code for errors of this kind, listed at [`CrustCoreErr::code`].
+    pub fn code(self) -> &'static str {
+        match self {
+            ErrorKind::UnexpectedCharacter => "E0001",
+            ErrorKind::UnterminatedString => "E0002",
+            ErrorKind::InvalidLineDirective => "E0003",
+            ErrorKind::UnterminatedComment => "E0004",
+            ErrorKind::InvalidLiteral => "E0005",
+            ErrorKind::Expected => "E0101",
+            ErrorKind::NestingTooDeep => "E0102",
+            ErrorKind::InvalidTarget => "E0103",
+            ErrorKind::TopLevelReturn => "E0104",
+            ErrorKind::TypeMismatch => "E0105",
+            ErrorKind::DuplicateParameter => "E0106",
+            ErrorKind::InvalidOperation => "E0200",
+            ErrorKind::UndefinedVariable => "E0201",
+            ErrorKind::DivisionByZero => "E0202",
+            ErrorKind::IntegerOverflow => "E0203",
+            ErrorKind::WrongArgumentCount => "E0204",
+            ErrorKind::ImmutableAssignment => "E0205",
+            ErrorKind::CallsTooDeep => "E0206",
+        }
+    }
+}
+
+impl CrustCoreErr {
+    /// Attributes the error to the file at `path`. Each error of a `Multi` is wrapped on its
+    /// own, so the leaves of the result all name their file.
+    pub fn in_file(self, path: &Path) -> CrustCoreErr {
+        match self {
+            CrustCoreErr::Multi { errors } => CrustCoreErr::Multi {
+                errors: errors
+                    .into_iter()
+                    .map(|error| error.in_file(path))
+                    .collect(),
+            },
+            error => CrustCoreErr::File {
+                path: path.to_path_buf(),
+                error: Box::new(error),
+            },
+        }
+    }
+
+    /// The line the error points at. For `Multi` that is its earliest child, or 0 when empty.
+    pub fn line(&self) -> usize {
+        match self {
+            CrustCoreErr::Multi { errors } => errors.iter().map(Self::line).min().unwrap_or(0),
+            CrustCoreErr::File { error, .. } => error.line(),
+            CrustCoreErr::Io { .. } => 0,
+            CrustCoreErr::Scan { line, .. }
+            | CrustCoreErr::Parse { line, .. }
+            | CrustCoreErr::Runtime { line, .. } => *line,
+        }
+    }
+
+    /// A stable code naming the [`ErrorKind`] of the error, for looking it up or filtering it
+    /// out.
+    ///
+    /// | code  | error                                               |
+    /// |-------|-----------------------------------------------------|
+    /// | E0001 | unexpected character                                |
+    /// | E0002 | unterminated string                                 |
+    /// | E0003 | invalid line directive                              |
+    /// | E0004 | unterminated block comment                          |
+    /// | E0005 | a malformed literal                                 |
+    /// | E0101 | expected a token or expression                      |
+    /// | E0102 | nesting too deep                                    |
+    /// | E0103 | invalid assignment or increment target              |
+    /// | E0104 | `return` outside a function                         |
+    /// | E0105 | a value of the wrong type for an annotation         |
+    /// | E0106 | a parameter name used twice                         |
+    /// | E0200 | any other runtime error                             |
+    /// | E0201 | undefined variable                                  |
+    /// | E0202 | division by zero                                    |
+    /// | E0203 | integer overflow                                    |
+    /// | E0204 | wrong number of arguments                           |
+    /// | E0205 | assignment to an immutable variable or constant     |
+    /// | E0206 | calls nested too deep                               |
+    /// | E0301 | a file could not be read                            |
+    ///
+    /// A `File` has the code of the error in it and a `Multi` that of its first error in
+    /// source order, or `E0000` when it is empty.
+    pub fn code(&self) -> &'static str {
+        match self {
+            CrustCoreErr::Multi { errors } => in_source_order(errors)
+                .first()
+                .map_or("E0000", |error| error.code()),
+            CrustCoreErr::File { error, .. } => error.code(),
+            CrustCoreErr::Scan { kind, .. }
+            | CrustCoreErr::Parse { kind, .. }
+            | CrustCoreErr::Runtime { kind, .. } => kind.code(),
+            CrustCoreErr::Io { .. } => "E0301",
+        }
+    }
+
+    /// The leaf errors, with nested `Multi`s flattened, in the order they were collected.
+    pub fn iter_diagnostics(&self) -> impl Iterator<Item = &CrustCoreErr> {
+        self.leaves()
+    }
+
+    fn leaves(&self) -> Box<dyn Iterator<Item = &CrustCoreErr> + '_> {
+        match self {
+            CrustCoreErr::Multi { errors } => Box::new(errors.iter().flat_map(Self::leaves)),
+            leaf => Box::new(std::iter::once(leaf)),
+        }
+    }
+
+    /// Formats the error like `Display` with its [`code`](Self::code) added, followed, for an
+    /// error that knows where it is, by the source line and a run of carets under the span. A
+    /// scan error is marked by one caret at its offset, and a span running past its first line
+    /// is only underlined to the end of that line.
+    pub fn render(&self, source: &str) -> String {
+        match self {
+            CrustCoreErr::Multi { errors } => in_source_order(errors)
+                .iter()
+                .map(|error| error.render(source))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            CrustCoreErr::File { path, error } => {
+                format!("{}: {}", path.display(), error.render(source))
+            }
+            CrustCoreErr::Scan { offset, .. } => self.snippet(source, *offset..*offset),
+            CrustCoreErr::Parse {
+                span: Some(span), ..
+            }
+            | CrustCoreErr::Runtime {
+                span: Some(span), ..
+            } => self.snippet(source, span.clone()),
+            _ => self.headline(),
+        }
+    }
+
+    fn snippet(&self, source: &str, span: Range<usize>) -> String {
+        let map = SourceMap::new(source);
+        let (line, start) = map.location(span.start);
+        let text = map.line_text(line);
+        let end = match map.location(span.end) {
+            (end_line, end) if end_line == line => end,
+            _ => text.chars().count() + 1,
+        };
+        format!(
+            "{}\n    {text}\n    {}{}",
+            self.headline(),
+            " ".repeat(start - 1),
+            "^".repeat(end.saturating_sub(start).max(1))
+        )
+    }
+}
+
+impl fmt::Display for CrustCoreErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.describe(f, false)
+    }
+}
+
+impl CrustCoreErr {
+    /// Writes the error's one-line description, or one line per error of a `Multi`, with the
+    /// code in brackets after `Error` when `with_code` is set.
+    fn describe(&self, f: &mut impl fmt::Write, with_code: bool) -> fmt::Result {
+        let code = if with_code {
+            format!("[{}]", self.code())
+        } else {
+            String::new()
+        };
+        match self {
+            CrustCoreErr::Multi { errors } => {
+                for (index, error) in in_source_order(errors).iter().enumerate() {
+                    if index > 0 {
+                        writeln!(f)?;
+                    }
+                    error.describe(f, with_code)?;
+                }
+                Ok(())
+            }
+            CrustCoreErr::Scan { line, message, .. }
+            | CrustCoreErr::Parse { line, message, .. } => {
+                write!(f, "[line {line}] Error{code}: {message}")
+            }
+            CrustCoreErr::Runtime { line, message, .. } => {
+                write!(f, "[line {line}] Runtime error{code}: {message}")
+            }
+            CrustCoreErr::File { path, error } => {
+                write!(f, "{}: ", path.display())?;
+                error.describe(f, with_code)
+            }
+            CrustCoreErr::Io { message } => write!(f, "I/O error{code}: {message}"),
+        }
+    }
+
+    /// The description [`render`](Self::render) starts with.
+    fn headline(&self) -> String {
+        let mut headline = String::new();
+        self.describe(&mut headline, true)
+            .expect("writing to a String never fails");
+        headline
+    }
+}
+
+/// Errors sorted by file and then top to bottom, keeping the order they were found in within a
+/// line.
+fn in_source_order(errors: &[CrustCoreErr]) -> Vec<&CrustCoreErr> {
+    let mut errors = errors.iter().collect::<Vec<_>>();
+    errors.sort_by_key(|error| {
+        let path = match error {
+            CrustCoreErr::File { path, .. } => Some(path),
+            _ => None,
+        };
+        (path, error.line())
+    });
+    errors
 }
 
 pub type CrustCoreResult<T = ()> = Result<T, CrustCoreErr>;
+
+/// The Levenshtein distance between two strings: how many single-character insertions,
+/// deletions or substitutions turn one into the other.
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// A non-fatal diagnostic, reported to the user without stopping execution.
+#[derive(Debug, PartialEq)]
+pub struct CrustCoreWarning {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for CrustCoreWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[line {}] Warning: {}", self.line, self.message)
+    }
+}
+
+/// A list of warnings whose clones all add to the same list, so a run can collect warnings that
+/// its caller reads back, and reports or ignores, afterwards.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SharedWarnings {
+    warnings: Rc<RefCell<Vec<CrustCoreWarning>>>,
+}
+
+impl SharedWarnings {
+    pub fn extend(&self, warnings: impl IntoIterator<Item = CrustCoreWarning>) {
+        self.warnings.borrow_mut().extend(warnings);
+    }
+
+    /// Removes and returns the warnings collected so far.
+    pub fn take(&self) -> Vec<CrustCoreWarning> {
+        self.warnings.take()
+    }
+}
+
+/// An in-memory `Write` sink whose clones all append to the same buffer, so the interpreter
+/// can own one end while the caller reads back what was written.
+#[derive(Clone, Default)]
+pub(crate) struct SharedBuffer {
+    buffer: Rc<RefCell<Vec<u8>>>,
+}
+
+impl SharedBuffer {
+    pub(crate) fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.buffer.borrow()).into_owned()
+    }
+}
+
+impl io::Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Maps byte offsets in a source string back to lines and columns. Line starts are computed
+/// once up front, so every lookup is a binary search rather than a rescan of the source. Lines
+/// end at `\n`, `\r\n` or a lone `\r`, as in the scanner.
+pub struct SourceMap<'a> {
+    source: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> SourceMap<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let bytes = source.as_bytes();
+        let line_starts = std::iter::once(0)
+            .chain(
+                bytes
+                    .iter()
+                    .enumerate()
+                    .filter(|&(index, &byte)| {
+                        byte == b'\n' || (byte == b'\r' && bytes.get(index + 1) != Some(&b'\n'))
+                    })
+                    .map(|(index, _)| index + 1),
+            )
+            .collect();
+        Self {
+            source,
+            line_starts,
+        }
+    }
+
+    /// The 1-based line and column of byte `offset`, counting columns in characters. Offsets
+    /// past the end map to the position just after the last character.
+    pub fn location(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.source.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        let line_start = self.line_starts[line - 1];
+        let column = self
+            .source
+            .get(line_start..offset)
+            .map_or(offset - line_start, |text| text.chars().count());
+        (line, column + 1)
+    }
+
+    /// The text of 1-based `line` without its line terminator, or `""` if there is no such line.
+    pub fn line_text(&self, line: usize) -> &'a str {
+        let Some(&start) = line
+            .checked_sub(1)
+            .and_then(|index| self.line_starts.get(index))
+        else {
+            return "";
+        };
+        let end = self
+            .line_starts
+            .get(line)
+            .map_or(self.source.len(), |&next| next - 1);
+        let text = &self.source[start..end];
+        text.strip_suffix('\r').unwrap_or(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iter_diagnostics_flattens_nested_multis() {
+        let scan = |line| CrustCoreErr::Scan {
+            kind: ErrorKind::InvalidLiteral,
+            line,
+            offset: 0,
+            message: format!("error {line}"),
+        };
+        let error = CrustCoreErr::Multi {
+            errors: vec![
+                scan(3),
+                CrustCoreErr::Multi {
+                    errors: vec![scan(1), scan(2)],
+                },
+            ],
+        };
+        let lines = error
+            .iter_diagnostics()
+            .map(CrustCoreErr::line)
+            .collect::<Vec<_>>();
+        assert_eq!(lines, [3, 1, 2]);
+        assert_eq!(scan(4).iter_diagnostics().count(), 1);
+    }
+
+    #[test]
+    fn multi_renders_in_line_order() {
+        let parse = |line: usize, message: &str| CrustCoreErr::Parse {
+            kind: ErrorKind::Expected,
+            line,
+            message: message.to_string(),
+            span: None,
+        };
+        let error = CrustCoreErr::Multi {
+            errors: vec![
+                parse(3, "third"),
+                CrustCoreErr::Scan {
+                    kind: ErrorKind::UnexpectedCharacter,
+                    line: 1,
+                    offset: 2,
+                    message: "first".to_string(),
+                },
+                parse(3, "fourth"),
+                parse(2, "second"),
+            ],
+        };
+        assert_eq!(error.line(), 1);
+        assert_eq!(
+            error.to_string(),
+            "[line 1] Error: first\n[line 2] Error: second\n\
+             [line 3] Error: third\n[line 3] Error: fourth"
+        );
+    }
+
+    #[test]
+    fn scan_errors_render_under_their_character() {
+        let source = "let a = 1;\nprint a @ \"é\" § 2;";
+        let (_, errors) = crate::scan(source);
+        let error = CrustCoreErr::Multi { errors };
+        assert_eq!(
+            error.render(source),
+            "[line 2] Error[E0001]: Unexpected character '@'\n    print a @ \"é\" § 2;\n            ^\n\
+             [line 2] Error[E0001]: Unexpected character '§'\n    print a @ \"é\" § 2;\n                  ^"
+        );
+    }
+
+    #[test]
+    fn errors_have_stable_codes() {
+        let scan_error = |source| crate::scan(source).1.remove(0);
+        assert_eq!(scan_error("print \"abc").code(), "E0002");
+        assert_eq!(scan_error("print @;").code(), "E0001");
+        assert_eq!(scan_error("print 1; /* 2").code(), "E0004");
+        assert_eq!(scan_error("print 5.5i;").code(), "E0005");
+
+        let parse_error = crate::eval("print (1 + 2;").unwrap_err();
+        assert_eq!(parse_error.code(), "E0101");
+        assert_eq!(
+            parse_error.render("print (1 + 2;"),
+            "[line 1] Error[E0101]: Expected ')' after expression\n    print (1 + 2;\n               ^"
+        );
+        assert_eq!(crate::eval("return 1;").unwrap_err().code(), "E0104");
+        assert_eq!(
+            crate::eval("let x: Int = \"a\";").unwrap_err().code(),
+            "E0105"
+        );
+        assert_eq!(crate::eval("fn f(a, a) {}").unwrap_err().code(), "E0106");
+        assert_eq!(crate::eval("1 / 0").unwrap_err().code(), "E0202");
+        assert_eq!(crate::eval("nope").unwrap_err().code(), "E0201");
+
+        let io = CrustCoreErr::Io {
+            message: "denied".to_string(),
+        }
+        .in_file(Path::new("a.crust"));
+        assert_eq!(io.code(), "E0301");
+        assert_eq!(io.render(""), "a.crust: I/O error[E0301]: denied");
+        assert_eq!(CrustCoreErr::Multi { errors: vec![] }.code(), "E0000");
+
+        // The code comes from the kind alone, whatever the message says.
+        let reworded = CrustCoreErr::Runtime {
+            kind: ErrorKind::DivisionByZero,
+            line: 1,
+            message: "Expected a non-zero divisor".to_string(),
+            span: None,
+        };
+        assert_eq!(reworded.code(), "E0202");
+    }
+
+    #[test]
+    fn source_map_locations() {
+        let map = SourceMap::new("let a = 1;\nprint a;\n");
+        assert_eq!(map.location(0), (1, 1));
+        assert_eq!(map.location(4), (1, 5));
+        assert_eq!(map.location(11), (2, 1));
+        assert_eq!(map.location(17), (2, 7));
+        // The end of the source is the start of the empty line after the final newline.
+        assert_eq!(map.location(20), (3, 1));
+        assert_eq!(map.location(100), (3, 1));
+    }
+
+    #[test]
+    fn source_map_line_text() {
+        let map = SourceMap::new("let a = 1;\r\nprint a;\r\nprint 2;");
+        assert_eq!(map.line_text(1), "let a = 1;");
+        assert_eq!(map.line_text(2), "print a;");
+        assert_eq!(map.line_text(3), "print 2;");
+        assert_eq!(map.line_text(0), "");
+        assert_eq!(map.line_text(4), "");
+        assert_eq!(map.location(12), (2, 1));
+        assert_eq!(map.location(28), (3, 7));
+    }
+
+    #[test]
+    fn source_map_lone_carriage_returns() {
+        let map = SourceMap::new("a\rbc\rd");
+        assert_eq!(map.location(3), (2, 2));
+        assert_eq!(map.line_text(2), "bc");
+        assert_eq!(map.line_text(3), "d");
+    }
+
+    #[test]
+    fn source_map_counts_characters() {
+        let map = SourceMap::new("print \"é\" @;");
+        assert_eq!(map.location(11), (1, 11));
+    }
+}
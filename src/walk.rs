@@ -0,0 +1,200 @@
+//! A traversal of the whole tree for passes that only care about a few kinds of node, where
+//! implementing every method of [`Visitor`](crate::ast::Visitor) would be mostly boilerplate.
+//!
+//! A [`Walk`] overrides the methods for what it looks at and calls the matching `walk_*`
+//! function to carry on into the children:
+//!
+//! ```
+//! use crust::{
+//!     ast::Expression,
+//!     parser::Parser,
+//!     scanner::Scanner,
+//!     walk::{walk_expression, Walk},
+//! };
+//!
+//! #[derive(Default)]
+//! struct CountLiterals(usize);
+//!
+//! impl Walk for CountLiterals {
+//!     fn expression(&mut self, expr: &Expression) {
+//!         if let Expression::Literal { .. } = expr {
+//!             self.0 += 1;
+//!         }
+//!         walk_expression(self, expr);
+//!     }
+//! }
+//!
+//! let tokens = Scanner::new("let x = 1; fn f(y) { return x + y * 2; }").scan_tokens().unwrap();
+//! let program = Parser::new(tokens).parse_program().unwrap();
+//! let mut count = CountLiterals::default();
+//! count.program(&program);
+//! assert_eq!(count.0, 2);
+//! ```
+
+use crate::ast::{Expression, Statement};
+
+/// Visits every statement and expression of a tree, parents before their children. The default
+/// methods only recurse.
+pub trait Walk {
+    fn program(&mut self, program: &[Statement]) {
+        for statement in program {
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        walk_statement(self, statement);
+    }
+
+    fn expression(&mut self, expr: &Expression) {
+        walk_expression(self, expr);
+    }
+}
+
+/// Walks the statements and expressions directly inside `statement`.
+pub fn walk_statement<W: Walk + ?Sized>(walker: &mut W, statement: &Statement) {
+    match statement {
+        Statement::Block { statements, .. }
+        | Statement::Function {
+            body: statements, ..
+        } => walker.program(statements),
+        Statement::Expression { expr }
+        | Statement::Print { expr }
+        | Statement::Const {
+            initializer: expr, ..
+        } => walker.expression(expr),
+        Statement::For {
+            start, end, body, ..
+        } => {
+            walker.expression(start);
+            walker.expression(end);
+            walker.program(body);
+        }
+        Statement::Let { initializer, .. } => {
+            if let Some(initializer) = initializer {
+                walker.expression(initializer);
+            }
+        }
+        Statement::Return { value, .. } => {
+            if let Some(value) = value {
+                walker.expression(value);
+            }
+        }
+    }
+}
+
+/// Walks the expressions and statements directly inside `expr`, in source order.
+pub fn walk_expression<W: Walk + ?Sized>(walker: &mut W, expr: &Expression) {
+    match expr {
+        Expression::Assign { value, .. } => walker.expression(value),
+        Expression::Binary { left, right, .. } | Expression::Logical { left, right, .. } => {
+            walker.expression(left);
+            walker.expression(right);
+        }
+        Expression::Block {
+            statements, value, ..
+        } => {
+            walker.program(statements);
+            if let Some(value) = value {
+                walker.expression(value);
+            }
+        }
+        Expression::Call { callee, args, .. } => {
+            walker.expression(callee);
+            for arg in args {
+                walker.expression(arg);
+            }
+        }
+        Expression::Conditional {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            walker.expression(condition);
+            walker.expression(then_branch);
+            walker.expression(else_branch);
+        }
+        Expression::Grouping { expr } | Expression::Unary { right: expr, .. } => {
+            walker.expression(expr)
+        }
+        Expression::Index { target, index, .. } => {
+            walker.expression(target);
+            walker.expression(index);
+        }
+        Expression::Interpolation { parts, .. } => {
+            for part in parts {
+                walker.expression(part);
+            }
+        }
+        Expression::Lambda { body, .. } => walker.program(body),
+        Expression::Literal { .. } | Expression::Path { .. } | Expression::Variable { .. } => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crust_grammar::symbol::Symbol;
+
+    use super::*;
+    use crate::{parser::Parser, scanner::Scanner};
+
+    fn parse(source: &str) -> Vec<Statement> {
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+        Parser::new(tokens).parse_program().unwrap()
+    }
+
+    /// The names of the variables read, in the order they are walked.
+    #[derive(Default)]
+    struct Reads(Vec<Symbol>);
+
+    impl Walk for Reads {
+        fn expression(&mut self, expr: &Expression) {
+            if let Expression::Variable { name, .. } = expr {
+                self.0.push(*name);
+            }
+            walk_expression(self, expr);
+        }
+    }
+
+    #[test]
+    fn walks_every_expression_in_order() {
+        let program = parse(
+            "let a = b;\nfor i in c..d { print e ? f : g; }\n\
+             fn h() { return { i; j }; }\nk(l, fn() { m })[n];\nprint \"${o}\";",
+        );
+        let mut reads = Reads::default();
+        reads.program(&program);
+        let names = reads
+            .0
+            .iter()
+            .map(|name| name.to_string())
+            .collect::<String>();
+        assert_eq!(names, "bcdefgijklmno");
+    }
+
+    #[test]
+    fn overriding_a_method_can_stop_the_walk() {
+        /// Reads outside of function bodies.
+        #[derive(Default)]
+        struct TopLevelReads(Vec<Symbol>);
+
+        impl Walk for TopLevelReads {
+            fn statement(&mut self, statement: &Statement) {
+                if !matches!(statement, Statement::Function { .. }) {
+                    walk_statement(self, statement);
+                }
+            }
+
+            fn expression(&mut self, expr: &Expression) {
+                if let Expression::Variable { name, .. } = expr {
+                    self.0.push(*name);
+                }
+                walk_expression(self, expr);
+            }
+        }
+
+        let mut reads = TopLevelReads::default();
+        reads.program(&parse("fn f() { print a; }\nprint b;"));
+        assert_eq!(reads.0, vec![Symbol::intern("b")]);
+    }
+}
@@ -0,0 +1,66 @@
+use crust::{eval, run, run_capture, util::CrustCoreErr, Value};
+
+#[test]
+fn eval_returns_final_expression() {
+    assert_eq!(eval("1 + 2 * 3").unwrap(), Value::Integer(7));
+    assert_eq!(
+        eval("let x = \"a\";\nx * 3;").unwrap(),
+        Value::Str("aaa".to_string())
+    );
+    assert_eq!(eval("fn f() { return 2; } f()").unwrap(), Value::Integer(2));
+    assert_eq!(eval("let x = 1;").unwrap(), Value::Nil);
+}
+
+#[test]
+fn eval_interpolated_strings() {
+    assert_eq!(
+        eval("let n = 3; \"n=${n}\"").unwrap(),
+        Value::Str("n=3".to_string())
+    );
+    assert_eq!(
+        eval("let x = 1.5; \"${x * 2.0} ${x > 1.0} ${nil}\"").unwrap(),
+        Value::Str("3.0 true nil".to_string())
+    );
+    assert!(matches!(eval("\"n=${n\""), Err(CrustCoreErr::Multi { .. })));
+    assert!(matches!(
+        eval("let n = 3;\n\"n=${n n}\""),
+        Err(CrustCoreErr::Parse { line: 2, .. })
+    ));
+}
+
+#[test]
+fn empty_source_does_nothing() {
+    assert!(run("").is_ok());
+    assert_eq!(eval("").unwrap(), Value::Nil);
+    assert_eq!(eval("\n  \n").unwrap(), Value::Nil);
+}
+
+#[test]
+fn eval_reports_errors() {
+    assert!(matches!(
+        eval("1 +"),
+        Err(CrustCoreErr::Parse { line: 1, .. })
+    ));
+    assert!(matches!(
+        eval("let x = 1;\nx / 0"),
+        Err(CrustCoreErr::Runtime { line: 2, .. })
+    ));
+}
+
+#[test]
+fn long_chains_evaluate() {
+    let sum = vec!["1"; 1000].join(" + ");
+    assert_eq!(eval(&sum).unwrap(), Value::Integer(1000));
+    let text = vec!["\"a\""; 1000].join(" + ");
+    assert_eq!(eval(&text).unwrap(), Value::Str("a".repeat(1000)));
+    let all = vec!["true"; 1000].join(" and ");
+    assert_eq!(eval(&all).unwrap(), Value::Bool(true));
+}
+
+#[test]
+fn char_literals_are_one_character_strings() {
+    assert_eq!(eval("'a'").unwrap(), Value::Str("a".to_string()));
+    assert_eq!(eval("'a' + \"b\"").unwrap(), Value::Str("ab".to_string()));
+    assert_eq!(eval("'é' == \"é\"").unwrap(), Value::Bool(true));
+    assert_eq!(run_capture("print 'a';\nprint '\\n';").unwrap(), "a\n\n\n");
+}
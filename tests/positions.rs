@@ -0,0 +1,78 @@
+//! Where tokens say they came from, as a contract for tools that map them back into the
+//! source. The expected offsets are counted by hand from `SOURCE`, in bytes.
+
+use crust::{eval, scanner::Scanner, token::SourceToken, token::Token};
+
+// Byte offsets of each line:
+//   line 1   0: `let total = 42;`
+//   line 2  16: `print "é\n`         (`é` is two bytes, then a `\n` escape and a real newline)
+//   line 3  28: `x";`
+//   line 4  32: `total = total + 1.5;`
+const SOURCE: &str = "let total = 42;\nprint \"é\\n\nx\";\ntotal = total + 1.5;\n";
+
+fn tokens() -> Vec<SourceToken> {
+    Scanner::new(SOURCE).scan_tokens().unwrap()
+}
+
+/// The `(offset, length, line)` of `token`, checking that its lexeme is the text it spans.
+fn position(token: &SourceToken) -> (usize, usize, usize) {
+    assert_eq!(
+        &SOURCE[token.offset..token.offset + token.length],
+        token.lexeme
+    );
+    (token.offset, token.length, token.line)
+}
+
+#[test]
+fn keywords_and_identifiers() {
+    let tokens = tokens();
+    assert_eq!(tokens[0].token, Token::Let);
+    assert_eq!(position(&tokens[0]), (0, 3, 1));
+    assert_eq!(tokens[1].token, Token::Identifier("total".into()));
+    assert_eq!(position(&tokens[1]), (4, 5, 1));
+    assert_eq!(tokens[5].token, Token::Print);
+    assert_eq!(position(&tokens[5]), (16, 5, 2));
+    assert_eq!(tokens[8].token, Token::Identifier("total".into()));
+    assert_eq!(position(&tokens[8]), (32, 5, 4));
+    assert_eq!(tokens[10].token, Token::Identifier("total".into()));
+    assert_eq!(position(&tokens[10]), (40, 5, 4));
+}
+
+#[test]
+fn numbers() {
+    let tokens = tokens();
+    assert_eq!(tokens[3].token, Token::Integer(42));
+    assert_eq!(position(&tokens[3]), (12, 2, 1));
+    assert_eq!(tokens[12].token, Token::Float(1.5));
+    assert_eq!(position(&tokens[12]), (48, 3, 4));
+}
+
+#[test]
+fn string_spanning_lines() {
+    let tokens = tokens();
+    // The lexeme is the raw source, quotes and escape included, while the value is decoded.
+    // A string spanning lines reports the line it ends on.
+    assert_eq!(tokens[6].token, Token::String("é\n\nx".into()));
+    assert_eq!(tokens[6].lexeme, "\"é\\n\nx\"");
+    assert_eq!(position(&tokens[6]), (22, 8, 3));
+    assert_eq!(tokens[7].token, Token::Semicolon);
+    assert_eq!(position(&tokens[7]), (30, 1, 3));
+}
+
+#[test]
+fn end_of_file_is_past_the_last_character() {
+    let tokens = tokens();
+    let eof = tokens.last().unwrap();
+    assert_eq!(eof.token, Token::Eof);
+    assert_eq!((eof.offset, eof.length, eof.line), (SOURCE.len(), 0, 5));
+}
+
+#[test]
+fn spans_render_under_their_token() {
+    let source = "let total = 42;\nprint \"é\" + total / 0;";
+    let error = eval(source).unwrap_err();
+    assert_eq!(
+        error.render(source),
+        "[line 2] Runtime error[E0202]: Division by zero\n    print \"é\" + total / 0;\n                      ^"
+    );
+}
@@ -0,0 +1,38 @@
+//! Property tests feeding arbitrary input to the scanner, which must report bad input as
+//! errors rather than panicking, and must make progress on every character.
+use crust::scanner::Scanner;
+use proptest::prelude::*;
+
+/// Scans `source`, checking the invariants that hold for any input.
+fn scan(source: &str) {
+    Scanner::new(source).needs_more_input();
+    Scanner::new(source).emit_newlines(true).scan();
+    let (tokens, _errors) = Scanner::new(source).scan();
+    // Every token but `Eof` covers at least one byte, so there can be no more than that.
+    assert!(tokens.len() <= source.len() + 1);
+    assert!(tokens.last().is_some_and(|token| token.lexeme.is_empty()));
+    for token in &tokens {
+        assert!(source
+            .get(token.offset..token.offset + token.length)
+            .is_some());
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(2048))]
+
+    #[test]
+    fn scanner_never_panics_on_utf8(source in any::<String>()) {
+        scan(&source);
+    }
+
+    #[test]
+    fn scanner_never_panics_on_bytes(bytes in proptest::collection::vec(any::<u8>(), 0..256)) {
+        scan(&String::from_utf8_lossy(&bytes));
+    }
+
+    #[test]
+    fn scanner_never_panics_on_source_like_text(source in r#"[a-z0-9_ \t\r\n"'\\.#!/=<>&|+\-*(){}\[\]:;?é]{0,64}"#) {
+        scan(&source);
+    }
+}